@@ -12,4 +12,4 @@ JSON_FILE="$2" # This script ignores this argument based on your requirements
 FUNC_NAME="$3"
 
 # Run the constants analysis
-cargo run --bin constants "$JSON_FILE" "$FUNC_NAME"
+cargo run --bin lirtool -- constants "$JSON_FILE" "$FUNC_NAME"
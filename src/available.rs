@@ -0,0 +1,50 @@
+use std::collections::{BTreeMap, HashMap};
+use crate::cfg::Cfg;
+use crate::lir::{*};
+use crate::reaching::Site;
+
+// a forward "must" analysis whose facts map keys to values, such as the copies or the expressions
+// available at a point: a fact holds at the start of a block only if it holds, with the same value,
+// at the end of every reachable predecessor. copy propagation and cse are both built on it.
+
+pub type Facts<K, V> = BTreeMap<K, V>;
+
+// the facts at the start of every reachable block. transfer updates the facts across one program
+// point; blocks are revisited in reverse postorder until no start changes.
+pub fn solve<K, V>(function: &Function, transfer: impl Fn(&mut Facts<K, V>, Site)) -> HashMap<String, Facts<K, V>>
+where
+    K: Ord + Clone,
+    V: Eq + Clone,
+{
+    let cfg = Cfg::new(function);
+    // None until a predecessor has been processed, standing for "every fact"
+    let mut starts: HashMap<String, Option<Facts<K, V>>> = cfg.rpo.iter().map(|bb| (bb.clone(), None)).collect();
+    starts.insert(cfg.entry.clone(), Some(Facts::new()));
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for bb_name in cfg.rpo.iter() {
+            let mut facts = match &starts[bb_name] {
+                Some(facts) => facts.clone(),
+                None => continue,
+            };
+            let bb = &function.body[bb_name];
+            bb.insts.iter().for_each(|inst| transfer(&mut facts, Site::Inst(inst)));
+            transfer(&mut facts, Site::Term(&bb.term));
+            for target in cfg.succs[bb_name].iter() {
+                let new = match &starts[target] {
+                    None => facts.clone(),
+                    Some(old) => old.iter()
+                        .filter(|(k, v)| facts.get(*k) == Some(*v))
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect(),
+                };
+                if starts[target].as_ref() != Some(&new) {
+                    starts.insert(target.clone(), Some(new));
+                    changed = true;
+                }
+            }
+        }
+    }
+    starts.into_iter().filter_map(|(bb, facts)| facts.map(|facts| (bb, facts))).collect()
+}
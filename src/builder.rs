@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use crate::lir::{*};
+use crate::validate::{self, ValidationError};
+
+// fluent construction of lir programs from rust, mainly for tests:
+//
+//     let mut program = ProgramBuilder::new();
+//     let g = program.global("g", Type::Int);
+//     let mut main = FunctionBuilder::new("main", Some(Type::Int));
+//     let x = main.local("x", Type::Int);
+//     main.block("entry").copy(&x, 1).arith(&x, ArithOp::Add, &x, &g).ret(Some(&x));
+//     program.add(main);
+//     let program = program.finish()?;
+//
+// a block is added to its function when its terminal is set, and finish() validates the program.
+
+impl From<i32> for Operand {
+    fn from(value: i32) -> Operand {
+        Operand::CInt(value)
+    }
+}
+
+impl From<&Variable> for Operand {
+    fn from(var: &Variable) -> Operand {
+        Operand::Var(var.clone())
+    }
+}
+
+pub fn pointer_to(typ: Type) -> Type {
+    Type::Pointer(Box::new(typ))
+}
+
+pub fn function_type(ret_ty: Option<Type>, param_ty: Vec<Type>) -> Type {
+    Type::Function(Box::new(FunctionType { ret_ty, param_ty }))
+}
+
+#[derive(Debug, Default)]
+pub struct ProgramBuilder {
+    program: Program,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> ProgramBuilder {
+        ProgramBuilder::default()
+    }
+
+    // declare a struct and return its type
+    pub fn structure(&mut self, name: &str, fields: &[(&str, Type)]) -> Type {
+        let fields = fields.iter()
+            .map(|(field, typ)| Field { name: field.to_string(), typ: typ.clone() })
+            .collect();
+        self.program.structs.insert(name.to_string(), fields);
+        Type::Struct(name.to_string())
+    }
+
+    pub fn global(&mut self, name: &str, typ: Type) -> Variable {
+        let var = Variable { name: name.to_string(), typ, scope: None };
+        self.program.globals.push(var.clone());
+        var
+    }
+
+    pub fn external(&mut self, name: &str, ret_ty: Option<Type>, param_ty: Vec<Type>) {
+        self.program.externs.insert(name.to_string(), function_type(ret_ty, param_ty));
+    }
+
+    pub fn add(&mut self, function: FunctionBuilder) {
+        self.program.functions.insert(function.function.id.clone(), function.function);
+    }
+
+    pub fn finish(self) -> Result<Program, Vec<ValidationError>> {
+        validate::validate(&self.program)?;
+        Ok(self.program)
+    }
+}
+
+#[derive(Debug)]
+pub struct FunctionBuilder {
+    function: Function,
+    allocs: usize,
+}
+
+impl FunctionBuilder {
+    pub fn new(name: &str, ret_ty: Option<Type>) -> FunctionBuilder {
+        let function = Function {
+            id: name.to_string(),
+            ret_ty,
+            params: vec![],
+            locals: vec![],
+            body: HashMap::new(),
+        };
+        FunctionBuilder { function, allocs: 0 }
+    }
+
+    fn var(&self, name: &str, typ: Type) -> Variable {
+        Variable { name: name.to_string(), typ, scope: Some(self.function.id.clone()) }
+    }
+
+    pub fn param(&mut self, name: &str, typ: Type) -> Variable {
+        let var = self.var(name, typ);
+        self.function.params.push(var.clone());
+        var
+    }
+
+    pub fn local(&mut self, name: &str, typ: Type) -> Variable {
+        let var = self.var(name, typ);
+        self.function.locals.push(var.clone());
+        var
+    }
+
+    // start the block named id; it replaces any block of the same name once terminated
+    pub fn block(&mut self, id: &str) -> BlockBuilder<'_> {
+        BlockBuilder { function: self, id: id.to_string(), insts: vec![] }
+    }
+}
+
+pub struct BlockBuilder<'a> {
+    function: &'a mut FunctionBuilder,
+    id: String,
+    insts: Vec<Instruction>,
+}
+
+impl<'a> BlockBuilder<'a> {
+    fn push(mut self, inst: Instruction) -> Self {
+        self.insts.push(inst);
+        self
+    }
+
+    pub fn addr_of(self, lhs: &Variable, rhs: &Variable) -> Self {
+        self.push(Instruction::AddrOf { lhs: lhs.clone(), rhs: rhs.clone() })
+    }
+
+    // allocation ids are numbered per function
+    pub fn alloc(self, lhs: &Variable, num: impl Into<Operand>) -> Self {
+        self.function.allocs += 1;
+        let id = self.function.var(&format!("_alloc{}", self.function.allocs), lhs.typ.clone());
+        self.push(Instruction::Alloc { lhs: lhs.clone(), num: num.into(), id })
+    }
+
+    pub fn copy(self, lhs: &Variable, op: impl Into<Operand>) -> Self {
+        self.push(Instruction::Copy { lhs: lhs.clone(), op: op.into() })
+    }
+
+    pub fn gep(self, lhs: &Variable, src: &Variable, idx: impl Into<Operand>) -> Self {
+        self.push(Instruction::Gep { lhs: lhs.clone(), src: src.clone(), idx: idx.into() })
+    }
+
+    pub fn arith(self, lhs: &Variable, aop: ArithOp, op1: impl Into<Operand>, op2: impl Into<Operand>) -> Self {
+        self.push(Instruction::Arith { lhs: lhs.clone(), aop, op1: op1.into(), op2: op2.into() })
+    }
+
+    pub fn load(self, lhs: &Variable, src: &Variable) -> Self {
+        self.push(Instruction::Load { lhs: lhs.clone(), src: src.clone() })
+    }
+
+    pub fn store(self, dst: &Variable, op: impl Into<Operand>) -> Self {
+        self.push(Instruction::Store { dst: dst.clone(), op: op.into() })
+    }
+
+    // the field's type is what lhs points to
+    pub fn gfp(self, lhs: &Variable, src: &Variable, field: &str) -> Self {
+        let typ = match &lhs.typ {
+            Type::Pointer(t) => (**t).clone(),
+            typ => typ.clone(),
+        };
+        let field = Variable { name: field.to_string(), typ, scope: None };
+        self.push(Instruction::Gfp { lhs: lhs.clone(), src: src.clone(), field })
+    }
+
+    pub fn cmp(self, lhs: &Variable, rop: RelaOp, op1: impl Into<Operand>, op2: impl Into<Operand>) -> Self {
+        self.push(Instruction::Cmp { lhs: lhs.clone(), rop, op1: op1.into(), op2: op2.into() })
+    }
+
+    pub fn call_ext(self, lhs: Option<&Variable>, callee: &str, args: Vec<Operand>) -> Self {
+        self.push(Instruction::CallExt { lhs: lhs.cloned(), ext_callee: callee.to_string(), args })
+    }
+
+    fn terminate(self, term: Terminal) {
+        let block = Block { id: self.id.clone(), insts: self.insts, term };
+        self.function.function.body.insert(self.id, block);
+    }
+
+    pub fn jump(self, target: &str) {
+        self.terminate(Terminal::Jump(target.to_string()))
+    }
+
+    pub fn branch(self, cond: impl Into<Operand>, tt: &str, ff: &str) {
+        self.terminate(Terminal::Branch { cond: cond.into(), tt: tt.to_string(), ff: ff.to_string() })
+    }
+
+    pub fn ret(self, op: Option<&Variable>) {
+        self.terminate(Terminal::Ret(op.map(Operand::from)))
+    }
+
+    pub fn ret_const(self, value: i32) {
+        self.terminate(Terminal::Ret(Some(Operand::CInt(value))))
+    }
+
+    pub fn call(self, lhs: Option<&Variable>, callee: &str, args: Vec<Operand>, next_bb: &str) {
+        self.terminate(Terminal::CallDirect {
+            lhs: lhs.cloned(),
+            callee: callee.to_string(),
+            args,
+            next_bb: next_bb.to_string(),
+        })
+    }
+
+    pub fn call_indirect(self, lhs: Option<&Variable>, callee: &Variable, args: Vec<Operand>, next_bb: &str) {
+        self.terminate(Terminal::CallIndirect {
+            lhs: lhs.cloned(),
+            callee: callee.clone(),
+            args,
+            next_bb: next_bb.to_string(),
+        })
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::lir::{*};
+
+// successors of a basic block in the order they appear in its terminal
+pub fn successors(term: &Terminal) -> Vec<String> {
+    match term {
+        Terminal::Jump(target) => vec![target.clone()],
+        Terminal::Branch { tt, ff, .. } => vec![tt.clone(), ff.clone()],
+        Terminal::CallDirect { next_bb, .. } |
+        Terminal::CallIndirect { next_bb, .. } => vec![next_bb.clone()],
+        Terminal::Ret(_) => vec![],
+    }
+}
+
+// redirects every edge of term from one block to another
+pub fn retarget(term: &mut Terminal, from: &str, to: &str) {
+    let targets: Vec<&mut String> = match term {
+        Terminal::Jump(target) => vec![target],
+        Terminal::Branch { tt, ff, .. } => vec![tt, ff],
+        Terminal::CallDirect { next_bb, .. } |
+        Terminal::CallIndirect { next_bb, .. } => vec![next_bb],
+        Terminal::Ret(_) => vec![],
+    };
+    targets.into_iter().filter(|t| *t == from).for_each(|t| *t = to.to_string());
+}
+
+// virtual node every returning block flows into, the root of the post-dominator tree
+pub const EXIT: &str = "<exit>";
+
+// control flow graph of a single function, restricted to the blocks reachable from entry
+#[derive(Debug)]
+pub struct Cfg {
+    pub entry: String,
+    pub succs: HashMap<String, Vec<String>>,
+    pub preds: HashMap<String, Vec<String>>,
+    // reachable blocks in reverse postorder, entry first
+    pub rpo: Vec<String>,
+    // immediate dominator of every reachable block except entry
+    pub idom: HashMap<String, String>,
+}
+
+impl Cfg {
+    pub fn new(function: &Function) -> Cfg {
+        let entry = "entry".to_string();
+        let mut succs: HashMap<String, Vec<String>> = HashMap::new();
+        function.body.iter().for_each(|(name, bb)| {
+            let mut targets = successors(&bb.term);
+            // a branch may name the same block twice
+            targets.dedup();
+            succs.insert(name.clone(), targets);
+        });
+
+        let rpo = reverse_postorder(&entry, &succs);
+        let reachable: HashSet<&String> = rpo.iter().collect();
+
+        let mut preds: HashMap<String, Vec<String>> = HashMap::new();
+        rpo.iter().for_each(|bb| { preds.entry(bb.clone()).or_default(); });
+        for bb in rpo.iter() {
+            for target in succs[bb].iter() {
+                if reachable.contains(target) {
+                    preds.entry(target.clone()).or_default().push(bb.clone());
+                }
+            }
+        }
+        succs.retain(|bb, _| reachable.contains(bb));
+
+        let idom = dominators(&entry, &rpo, &preds);
+        Cfg { entry, succs, preds, rpo, idom }
+    }
+
+    // children of every block in the dominator tree, sorted by reverse postorder
+    pub fn dom_tree(&self) -> HashMap<String, Vec<String>> {
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        self.rpo.iter().for_each(|bb| { children.entry(bb.clone()).or_default(); });
+        for bb in self.rpo.iter() {
+            if let Some(parent) = self.idom.get(bb) {
+                children.get_mut(parent).unwrap().push(bb.clone());
+            }
+        }
+        children
+    }
+
+    // true if a dominates b
+    pub fn dominates(&self, a: &str, b: &str) -> bool {
+        let mut current = b;
+        loop {
+            if current == a { return true; }
+            match self.idom.get(current) {
+                Some(parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    // dominance frontier of every reachable block (Cooper, Harvey and Kennedy)
+    pub fn dominance_frontiers(&self) -> HashMap<String, HashSet<String>> {
+        let mut frontiers: HashMap<String, HashSet<String>> = HashMap::new();
+        self.rpo.iter().for_each(|bb| { frontiers.entry(bb.clone()).or_default(); });
+        for bb in self.rpo.iter() {
+            let preds = &self.preds[bb];
+            if preds.len() < 2 { continue; }
+            for pred in preds.iter() {
+                let mut runner = pred;
+                while Some(runner) != self.idom.get(bb) {
+                    frontiers.get_mut(runner).unwrap().insert(bb.clone());
+                    match self.idom.get(runner) {
+                        Some(parent) => runner = parent,
+                        None => break,
+                    }
+                }
+            }
+        }
+        frontiers
+    }
+
+    // immediate post-dominator of every block that can reach a return; blocks whose ipdom is
+    // the virtual EXIT node are post-dominated by nothing else
+    pub fn post_dominators(&self) -> HashMap<String, String> {
+        let exit = EXIT.to_string();
+        let mut rev_succs: HashMap<String, Vec<String>> = HashMap::new();
+        let mut rev_preds: HashMap<String, Vec<String>> = HashMap::new();
+        rev_succs.insert(exit.clone(), vec![]);
+        for bb in self.rpo.iter() {
+            rev_succs.insert(bb.clone(), self.preds[bb].clone());
+            rev_preds.insert(bb.clone(), self.succs[bb].clone());
+            if self.succs[bb].is_empty() {
+                rev_succs.get_mut(&exit).unwrap().push(bb.clone());
+                rev_preds.get_mut(bb).unwrap().push(exit.clone());
+            }
+        }
+        rev_preds.insert(exit.clone(), vec![]);
+        let rpo = reverse_postorder(&exit, &rev_succs);
+        dominators(&exit, &rpo, &rev_preds)
+    }
+
+    // control dependence edges (Ferrante, Ottenstein and Warren) as (branch block, successor
+    // taken, dependent block): b depends on a if a branches to a path that must reach b and
+    // another path that may avoid it
+    pub fn control_dep_edges(&self) -> Vec<(String, String, String)> {
+        let ipdom = self.post_dominators();
+        let mut edges = vec![];
+        for a in self.rpo.iter() {
+            if self.succs[a].len() < 2 { continue; }
+            let stop = ipdom.get(a);
+            for b in self.succs[a].iter() {
+                let mut runner = Some(b);
+                while let Some(current) = runner {
+                    if Some(current) == stop || current == EXIT { break; }
+                    edges.push((a.clone(), b.clone(), current.clone()));
+                    runner = ipdom.get(current);
+                }
+            }
+        }
+        edges
+    }
+
+    // blocks each block is control dependent on
+    pub fn control_deps(&self) -> HashMap<String, BTreeSet<String>> {
+        let mut deps: HashMap<String, BTreeSet<String>> = HashMap::new();
+        self.rpo.iter().for_each(|bb| { deps.entry(bb.clone()).or_default(); });
+        for (a, _, b) in self.control_dep_edges() {
+            deps.get_mut(&b).unwrap().insert(a);
+        }
+        deps
+    }
+
+    // natural loops keyed by header: the header plus every block that reaches one of its back edges
+    // (an edge into a block that dominates its source) without going through the header
+    pub fn natural_loops(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let mut loops: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for bb in self.rpo.iter() {
+            for header in self.succs[bb].iter() {
+                if !self.dominates(header, bb) { continue; }
+                let body = loops.entry(header.clone()).or_insert_with(|| BTreeSet::from([header.clone()]));
+                let mut worklist = vec![bb.clone()];
+                while let Some(current) = worklist.pop() {
+                    if body.insert(current.clone()) {
+                        worklist.extend(self.preds[&current].iter().cloned());
+                    }
+                }
+            }
+        }
+        loops
+    }
+
+    // targets of retreating edges, those to a block no later in reverse postorder. every cycle
+    // has one, so an analysis that widens at them converges even on irreducible graphs
+    pub fn widening_points(&self) -> HashSet<String> {
+        let order: HashMap<&String, usize> = self.rpo.iter().enumerate().map(|(i, bb)| (bb, i)).collect();
+        self.rpo.iter()
+            .filter(|bb| self.preds[*bb].iter().any(|p| order[p] >= order[bb]))
+            .cloned()
+            .collect()
+    }
+
+    // nesting depth of every loop header: 1 for an outermost loop
+    pub fn loop_depths(&self) -> BTreeMap<String, usize> {
+        let loops = self.natural_loops();
+        loops.keys()
+            .map(|header| (header.clone(), loops.values().filter(|body| body.contains(header)).count()))
+            .collect()
+    }
+
+    // cyclomatic complexity of the reachable graph, edges - nodes + 2
+    pub fn cyclomatic_complexity(&self) -> usize {
+        let edges: usize = self.succs.values().map(|targets| targets.len()).sum();
+        edges + 2 - self.rpo.len()
+    }
+}
+
+fn reverse_postorder(entry: &str, succs: &HashMap<String, Vec<String>>) -> Vec<String> {
+    let mut visited: HashSet<String> = HashSet::new();
+    let mut postorder = vec![];
+    // a function without the entry block reaches nothing
+    if !succs.contains_key(entry) {
+        return postorder;
+    }
+    // iterative dfs so that long block chains do not overflow the stack
+    let mut stack: Vec<(String, usize)> = vec![(entry.to_string(), 0)];
+    visited.insert(entry.to_string());
+    while let Some((bb, next)) = stack.pop() {
+        let targets = succs.get(&bb).map(|t| t.as_slice()).unwrap_or(&[]);
+        if next < targets.len() {
+            stack.push((bb, next + 1));
+            let target = &targets[next];
+            if succs.contains_key(target) && visited.insert(target.clone()) {
+                stack.push((target.clone(), 0));
+            }
+        } else {
+            postorder.push(bb);
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+// iterative dominator computation over a graph given in reverse postorder
+fn dominators(entry: &String, rpo: &[String], preds: &HashMap<String, Vec<String>>) -> HashMap<String, String> {
+    let order: HashMap<&String, usize> = rpo.iter().enumerate().map(|(i, bb)| (bb, i)).collect();
+    let mut idom: HashMap<String, String> = HashMap::new();
+    idom.insert(entry.clone(), entry.clone());
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for bb in rpo.iter().skip(1) {
+            let mut new_idom: Option<String> = None;
+            for pred in preds[bb].iter() {
+                if !idom.contains_key(pred) { continue; }
+                new_idom = match new_idom {
+                    None => Some(pred.clone()),
+                    Some(current) => Some(intersect(&idom, &order, pred, &current)),
+                };
+            }
+            if let Some(new_idom) = new_idom {
+                if idom.get(bb) != Some(&new_idom) {
+                    idom.insert(bb.clone(), new_idom);
+                    changed = true;
+                }
+            }
+        }
+    }
+    idom.remove(entry);
+    idom
+}
+
+fn intersect(idom: &HashMap<String, String>, order: &HashMap<&String, usize>, a: &String, b: &String) -> String {
+    let mut finger1 = a;
+    let mut finger2 = b;
+    while finger1 != finger2 {
+        while order[finger1] > order[finger2] {
+            finger1 = &idom[finger1];
+        }
+        while order[finger2] > order[finger1] {
+            finger2 = &idom[finger2];
+        }
+    }
+    finger1.clone()
+}
@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use crate::domain::{self, AbstractValue};
+use crate::interp;
+use crate::lir::{*};
+use crate::report::OutputFormat;
+
+// the congruence analysis: x = rem (mod 2^log), combined with an interval into a reduced product.
+// only powers of two are used as moduli, since they divide 2^32 and so survive the wrap-around of
+// the interpreter's arithmetic; they are also what the alignment of a $gep index needs. a stride
+// such as i = i + 4 keeps i = 0 (mod 4) through loops, where the interval alone is widened away.
+// each side sharpens the other: the bounds of the interval move to the nearest values of the
+// congruence class, and a single remaining value pins the congruence down to it.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+pub enum Congruence {
+    Bottom,
+    // rem < 2^log; log 0 is any int, log 32 the int rem itself
+    Mod { log: u32, rem: u32 },
+}
+
+fn mask(log: u32) -> u32 {
+    if log >= 32 { u32::MAX } else { (1 << log) - 1 }
+}
+
+impl Congruence {
+    fn new(log: u32, rem: u32) -> Congruence {
+        let log = log.min(32);
+        Congruence::Mod { log, rem: rem & mask(log) }
+    }
+
+    // the int it stands for, if only one
+    pub fn value(&self) -> Option<i32> {
+        match self {
+            Congruence::Mod { log: 32, rem } => Some(*rem as i32),
+            _ => None,
+        }
+    }
+
+    // whether every int it stands for is a multiple of 2^log, e.g. a $gep index aligned to 2^log
+    pub fn aligned(&self, log: u32) -> bool {
+        match self {
+            Congruence::Bottom => true,
+            Congruence::Mod { log: l, rem } => *l >= log && rem & mask(log) == 0,
+        }
+    }
+}
+
+impl AbstractValue for Congruence {
+    fn top() -> Self { Congruence::new(0, 0) }
+    fn bottom() -> Self { Congruence::Bottom }
+    fn constant(value: i32) -> Self { Congruence::new(32, value as u32) }
+
+    // the largest power of two dividing the difference of the two classes
+    fn join(a: &Self, b: &Self) -> Self {
+        match (a, b) {
+            (Congruence::Bottom, _) => *b,
+            (_, Congruence::Bottom) => *a,
+            (Congruence::Mod { log: l1, rem: r1 }, Congruence::Mod { log: l2, rem: r2 }) => {
+                Congruence::new((*l1).min(*l2).min(r1.wrapping_sub(*r2).trailing_zeros()), *r1)
+            }
+        }
+    }
+
+    fn arith(op1: &Self, op2: &Self, aop: &ArithOp) -> Self {
+        let ((l1, r1), (l2, r2)) = match (op1, op2) {
+            (Congruence::Mod { log: l1, rem: r1 }, Congruence::Mod { log: l2, rem: r2 }) => ((*l1, *r1), (*l2, *r2)),
+            _ => return Congruence::Bottom,
+        };
+        match aop {
+            ArithOp::Add => Congruence::new(l1.min(l2), r1.wrapping_add(r2)),
+            ArithOp::Subtract => Congruence::new(l1.min(l2), r1.wrapping_sub(r2)),
+            // (r1 + 2^l1 s)(r2 + 2^l2 t) = r1 r2 + r2 2^l1 s + r1 2^l2 t + 2^(l1 + l2) s t
+            ArithOp::Multiply => {
+                let log = (l1 + r2.trailing_zeros()).min(l2 + r1.trailing_zeros()).min(l1 + l2);
+                Congruence::new(log, r1.wrapping_mul(r2))
+            }
+            // a division by zero faults; like the constant analysis, its result is unknown
+            ArithOp::Divide => match (op1.value(), op2.value()) {
+                (Some(a), Some(b)) => interp::arith(aop, a, b).map_or(Congruence::top(), Congruence::constant),
+                _ => Congruence::top(),
+            },
+        }
+    }
+
+    fn cmp(op1: &Self, op2: &Self, rop: &RelaOp) -> Self {
+        let ((l1, r1), (l2, r2)) = match (op1, op2) {
+            (Congruence::Mod { log: l1, rem: r1 }, Congruence::Mod { log: l2, rem: r2 }) => ((*l1, *r1), (*l2, *r2)),
+            _ => return Congruence::Bottom,
+        };
+        if let (Some(a), Some(b)) = (op1.value(), op2.value()) {
+            let holds = match rop {
+                RelaOp::Eq => a == b,
+                RelaOp::Neq => a != b,
+                RelaOp::Less => a < b,
+                RelaOp::LessEq => a <= b,
+                RelaOp::Greater => a > b,
+                RelaOp::GreaterEq => a >= b,
+            };
+            return Congruence::constant(holds as i32);
+        }
+        // ints of different classes are never equal
+        let distinct = (r1 ^ r2) & mask(l1.min(l2)) != 0;
+        match rop {
+            RelaOp::Eq if distinct => Congruence::constant(0),
+            RelaOp::Neq if distinct => Congruence::constant(1),
+            _ => Congruence::top(),
+        }
+    }
+
+    fn truth(&self) -> (bool, bool) {
+        match self {
+            Congruence::Bottom => (false, false),
+            Congruence::Mod { log: 32, rem: 0 } => (false, true),
+            // 0 is in the class of 0 only
+            Congruence::Mod { rem, .. } => (true, *rem == 0),
+        }
+    }
+
+    fn as_string(&self) -> String {
+        match self {
+            Congruence::Bottom => "Bottom".to_string(),
+            Congruence::Mod { log: 0, .. } => "Top".to_string(),
+            Congruence::Mod { log: 32, rem } => (*rem as i32).to_string(),
+            Congruence::Mod { log, rem } => format!("{} mod {}", rem, 1u64 << log),
+        }
+    }
+}
+
+const MIN: i64 = i32::MIN as i64;
+const MAX: i64 = i32::MAX as i64;
+
+// an interval of ints and a congruence class, reduced against each other
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+pub struct Strided {
+    pub lo: i32,
+    pub hi: i32,
+    pub congruence: Congruence,
+}
+
+impl Strided {
+    // the ints in [lo, hi] of the class, with the interval the whole int range if it does not fit
+    pub fn new(lo: i64, hi: i64, congruence: Congruence) -> Strided {
+        let (mut lo, mut hi) = if lo < MIN || hi > MAX { (MIN, MAX) } else { (lo, hi) };
+        let mut congruence = congruence;
+        if let Congruence::Mod { log, rem } = congruence {
+            let (modulus, rem) = (1i64 << log, rem as i64);
+            lo += (rem - lo).rem_euclid(modulus);
+            hi -= (hi - rem).rem_euclid(modulus);
+        }
+        if lo > hi || congruence == Congruence::Bottom {
+            return Strided::bottom();
+        }
+        if lo == hi {
+            congruence = Congruence::constant(lo as i32);
+        }
+        Strided { lo: lo as i32, hi: hi as i32, congruence }
+    }
+
+    pub fn is_bottom(&self) -> bool {
+        self.congruence == Congruence::Bottom
+    }
+
+    fn bounds(&self) -> (i64, i64) {
+        (self.lo as i64, self.hi as i64)
+    }
+
+    // the bounds of a / b over the non-zero divisors of b; none if b is 0
+    fn divide((a1, a2): (i64, i64), (b1, b2): (i64, i64)) -> Option<(i64, i64)> {
+        let divisors = [(b1, b2.min(-1)), (b1.max(1), b2)];
+        let quotients: Vec<i64> = divisors.iter()
+            .filter(|(lo, hi)| lo <= hi)
+            .flat_map(|(lo, hi)| [a1 / lo, a1 / hi, a2 / lo, a2 / hi])
+            .collect();
+        Some((*quotients.iter().min()?, *quotients.iter().max()?))
+    }
+
+    // the bounds of the result of aop before it wraps around, e.g. 2^31 for i32::MIN / -1; none if
+    // an operand is bottom or the only divisor is 0
+    pub fn exact(op1: &Strided, op2: &Strided, aop: &ArithOp) -> Option<(i64, i64)> {
+        if op1.is_bottom() || op2.is_bottom() {
+            return None;
+        }
+        let ((a1, a2), (b1, b2)) = (op1.bounds(), op2.bounds());
+        match aop {
+            ArithOp::Add => Some((a1 + b1, a2 + b2)),
+            ArithOp::Subtract => Some((a1 - b2, a2 - b1)),
+            ArithOp::Multiply => {
+                let products = [a1 * b1, a1 * b2, a2 * b1, a2 * b2];
+                Some((*products.iter().min().unwrap(), *products.iter().max().unwrap()))
+            }
+            ArithOp::Divide => Strided::divide((a1, a2), (b1, b2)),
+        }
+    }
+}
+
+impl AbstractValue for Strided {
+    fn top() -> Self { Strided::new(MIN, MAX, Congruence::top()) }
+    fn bottom() -> Self { Strided { lo: i32::MAX, hi: i32::MIN, congruence: Congruence::Bottom } }
+    fn constant(value: i32) -> Self { Strided::new(value as i64, value as i64, Congruence::constant(value)) }
+
+    fn join(a: &Self, b: &Self) -> Self {
+        match (a.is_bottom(), b.is_bottom()) {
+            (true, _) => *b,
+            (_, true) => *a,
+            _ => Strided::new(a.lo.min(b.lo) as i64, a.hi.max(b.hi) as i64, Congruence::join(&a.congruence, &b.congruence)),
+        }
+    }
+
+    // a bound that moved goes to the end of the int range, where the congruence then puts it
+    fn widen(old: &Self, new: &Self) -> Self {
+        if old.is_bottom() || new.is_bottom() {
+            return Strided::join(old, new);
+        }
+        let lo = if new.lo < old.lo { MIN } else { old.lo as i64 };
+        let hi = if new.hi > old.hi { MAX } else { old.hi as i64 };
+        Strided::new(lo, hi, Congruence::join(&old.congruence, &new.congruence))
+    }
+
+    // the bounds are computed without wrapping around; a result that does not fit is any int
+    fn arith(op1: &Self, op2: &Self, aop: &ArithOp) -> Self {
+        if op1.is_bottom() || op2.is_bottom() {
+            return Strided::bottom();
+        }
+        let (lo, hi) = Strided::exact(op1, op2, aop).unwrap_or((MIN, MAX));
+        Strided::new(lo, hi, Congruence::arith(&op1.congruence, &op2.congruence, aop))
+    }
+
+    fn cmp(op1: &Self, op2: &Self, rop: &RelaOp) -> Self {
+        if op1.is_bottom() || op2.is_bottom() {
+            return Strided::bottom();
+        }
+        let (a, b) = (op1, op2);
+        let (always, never) = match rop {
+            RelaOp::Eq => (a.lo == a.hi && b.lo == b.hi && a.lo == b.lo, a.hi < b.lo || b.hi < a.lo),
+            RelaOp::Neq => (a.hi < b.lo || b.hi < a.lo, a.lo == a.hi && b.lo == b.hi && a.lo == b.lo),
+            RelaOp::Less => (a.hi < b.lo, a.lo >= b.hi),
+            RelaOp::LessEq => (a.hi <= b.lo, a.lo > b.hi),
+            RelaOp::Greater => (a.lo > b.hi, a.hi <= b.lo),
+            RelaOp::GreaterEq => (a.lo >= b.hi, a.hi < b.lo),
+        };
+        let lo = if always { 1 } else { 0 };
+        let hi = if never { 0 } else { 1 };
+        Strided::new(lo, hi, Congruence::cmp(&a.congruence, &b.congruence, rop))
+    }
+
+    // a branch can only go where both sides allow
+    fn truth(&self) -> (bool, bool) {
+        let (may_be_true, may_be_false) = self.congruence.truth();
+        (may_be_true && (self.lo != 0 || self.hi != 0), may_be_false && self.lo <= 0 && self.hi >= 0)
+    }
+
+    fn as_string(&self) -> String {
+        if self.is_bottom() {
+            return "Bottom".to_string();
+        }
+        if let Some(value) = self.congruence.value() {
+            return value.to_string();
+        }
+        let interval = if self.bounds() == (MIN, MAX) { "Top".to_string() } else { format!("[{}, {}]", self.lo, self.hi) };
+        format!("({}, {})", interval, self.congruence.as_string())
+    }
+}
+
+pub type AbstractStore = domain::AbstractStore<Strided>;
+
+// the interval and congruence of every int in every block the worklist reached, and how many
+// blocks it processed
+pub fn congruence_analysis(program: &Program, function_name: &str) -> (HashMap<String, AbstractStore>, usize) {
+    domain::analyze(program, function_name)
+}
+
+// the index of every $gep the worklist reached, just before it, by block and instruction index
+pub fn gep_indices(program: &Program, function_name: &str) -> BTreeMap<(String, usize), Strided> {
+    let function = &program.functions[function_name];
+    let mut indices = BTreeMap::new();
+    for (block, stores) in domain::points::<Strided>(program, function_name) {
+        for (index, (inst, store)) in function.body[&block].insts.iter().zip(stores.iter()).enumerate() {
+            if let Instruction::Gep { idx, .. } = inst {
+                indices.insert((block.clone(), index), store.resolve_operand(idx));
+            }
+        }
+    }
+    indices
+}
+
+pub fn render(program: &Program, function_name: &str, format: OutputFormat) -> String {
+    domain::render::<Strided>("congruence", program, function_name, format)
+}
@@ -0,0 +1,120 @@
+use crate::constants::{AbstractStore, IntConstAbsVal};
+use crate::domain;
+use crate::lir::{*};
+
+// constant folding driven by the constants analysis: int variables known to be constant where they
+// are read become literals, $arith and $cmp with a constant result become $copy of it, and a $branch
+// on a constant becomes a $jump. blocks the analysis never reached are left as they are.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FoldStats {
+    // variable operands replaced by their constant
+    pub operands: usize,
+    // $arith and $cmp turned into $copy
+    pub folded: usize,
+    // $branch turned into $jump
+    pub branches: usize,
+}
+
+impl FoldStats {
+    fn add(&mut self, other: FoldStats) {
+        self.operands += other.operands;
+        self.folded += other.folded;
+        self.branches += other.branches;
+    }
+}
+
+// fold every function of program
+pub fn fold(program: &Program) -> (Program, FoldStats) {
+    let mut folded = program.clone();
+    let mut stats = FoldStats::default();
+    for name in program.functions.keys() {
+        let (function, function_stats) = fold_function(program, name);
+        folded.functions.insert(name.clone(), function);
+        stats.add(function_stats);
+    }
+    (folded, stats)
+}
+
+pub fn fold_function(program: &Program, function_name: &str) -> (Function, FoldStats) {
+    let mut function = program.functions[function_name].clone();
+    let stores = domain::points::<IntConstAbsVal>(program, function_name);
+    let mut folder = Folder { stats: FoldStats::default() };
+    for (bb_name, bb) in function.body.iter_mut() {
+        if let Some(stores) = stores.get(bb_name) {
+            folder.block(bb, stores);
+        }
+    }
+    (function, folder.stats)
+}
+
+struct Folder {
+    stats: FoldStats,
+}
+
+impl Folder {
+    // the constant operand stands for, if it is an int variable with a known value
+    fn operand(&mut self, op: &mut Operand, store: &AbstractStore) {
+        if let Operand::Var(v) = op {
+            if v.typ != Type::Int {
+                return;
+            }
+            if let IntConstAbsVal::IntConst(c) = store.resolve_operand(op) {
+                *op = Operand::CInt(c);
+                self.stats.operands += 1;
+            }
+        }
+    }
+
+    fn operands(&mut self, ops: &mut [Operand], store: &AbstractStore) {
+        ops.iter_mut().for_each(|op| self.operand(op, store));
+    }
+
+    // stores[i] holds before instruction i, and the last one before the terminal
+    fn block(&mut self, bb: &mut Block, stores: &[AbstractStore]) {
+        for (i, inst) in bb.insts.iter_mut().enumerate() {
+            let (before, after) = (&stores[i], &stores[i + 1]);
+            match inst {
+                Instruction::Copy { op, .. } |
+                Instruction::Store { op, .. } => self.operand(op, before),
+                Instruction::Alloc { num, .. } => self.operand(num, before),
+                Instruction::Gep { idx, .. } => self.operand(idx, before),
+                Instruction::CallExt { args, .. } => self.operands(args, before),
+                Instruction::Arith { lhs, op1, op2, .. } |
+                Instruction::Cmp { lhs, op1, op2, .. } => {
+                    let value = after.resolve_operand(&Operand::Var(lhs.clone()));
+                    match value {
+                        IntConstAbsVal::IntConst(c) if lhs.typ == Type::Int => {
+                            *inst = Instruction::Copy { lhs: lhs.clone(), op: Operand::CInt(c) };
+                            self.stats.folded += 1;
+                        }
+                        _ => {
+                            self.operand(op1, before);
+                            self.operand(op2, before);
+                        }
+                    }
+                }
+                Instruction::AddrOf { .. } |
+                Instruction::Load { .. } |
+                Instruction::Gfp { .. } => {}
+            }
+        }
+
+        let store = stores.last().unwrap();
+        match &mut bb.term {
+            Terminal::Branch { cond, tt, ff } => {
+                self.operand(cond, store);
+                if let Operand::CInt(c) = cond {
+                    let target = if *c != 0 { tt.clone() } else { ff.clone() };
+                    bb.term = Terminal::Jump(target);
+                    self.stats.branches += 1;
+                }
+            }
+            Terminal::CallDirect { args, .. } |
+            Terminal::CallIndirect { args, .. } => self.operands(args, store),
+            Terminal::Ret(Some(op)) => self.operand(op, store),
+            Terminal::Ret(None) |
+            Terminal::Jump(_) => {}
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::available::{self, Facts};
+use crate::lir::{*};
+use crate::reaching::{Effects, Memory, Site};
+
+// copy propagation: after x = $copy y, reads of x are replaced by y for as long as neither x nor y
+// may have been written, by an assignment, a store through a pointer or a call. copies of copies
+// resolve to the original source, so chains collapse, and copies of constants propagate the
+// constant. with Scope::Local the facts do not flow between blocks.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Local,
+    Global,
+}
+
+// each copied variable and the operand it currently equals
+type Copies = Facts<String, Operand>;
+
+pub fn propagate(program: &Program, scope: Scope) -> (Program, usize) {
+    let mut result = program.clone();
+    let mut replaced = 0;
+    for name in program.functions.keys() {
+        let (function, n) = propagate_function(program, name, scope);
+        result.functions.insert(name.clone(), function);
+        replaced += n;
+    }
+    (result, replaced)
+}
+
+// the function with its reads rewritten, and how many reads were replaced
+pub fn propagate_function(program: &Program, function_name: &str, scope: Scope) -> (Function, usize) {
+    let mut function = program.functions[function_name].clone();
+    let memory = Memory::new(program, &function);
+    let transfer = |copies: &mut Copies, site: Site| transfer(copies, site, &memory);
+    let starts = match scope {
+        Scope::Global => available::solve(&function, transfer),
+        Scope::Local => function.body.keys().map(|bb| (bb.clone(), Copies::new())).collect(),
+    };
+
+    let mut replaced = 0;
+    for (bb_name, bb) in function.body.iter_mut() {
+        let mut copies = match starts.get(bb_name) {
+            Some(copies) => copies.clone(),
+            None => continue,
+        };
+        for inst in bb.insts.iter_mut() {
+            replaced += rewrite_inst(inst, &copies);
+            transfer(&mut copies, Site::Inst(inst));
+        }
+        replaced += rewrite_term(&mut bb.term, &copies);
+    }
+    (function, replaced)
+}
+
+fn transfer(copies: &mut Copies, site: Site, memory: &Memory) {
+    let effects = Effects::of_site(site, memory);
+    for var in effects.strong_defs.iter().chain(effects.weak_defs.iter()) {
+        copies.remove(var);
+        copies.retain(|_, source| !matches!(source, Operand::Var(v) if v.name == *var));
+    }
+    if let Site::Inst(Instruction::Copy { lhs, op }) = site {
+        let source = resolve(copies, op);
+        if !matches!(&source, Operand::Var(v) if v.name == lhs.name) {
+            copies.insert(lhs.name.clone(), source);
+        }
+    }
+}
+
+fn resolve(copies: &Copies, op: &Operand) -> Operand {
+    match op {
+        Operand::Var(v) => copies.get(&v.name).cloned().unwrap_or_else(|| op.clone()),
+        Operand::CInt(_) => op.clone(),
+    }
+}
+
+fn operand(op: &mut Operand, copies: &Copies) -> usize {
+    let source = resolve(copies, op);
+    if source == *op {
+        return 0;
+    }
+    *op = source;
+    1
+}
+
+// a variable read as a pointer or a callee can only be replaced by another variable
+fn variable(var: &mut Variable, copies: &Copies) -> usize {
+    match copies.get(&var.name) {
+        Some(Operand::Var(source)) => {
+            *var = source.clone();
+            1
+        }
+        _ => 0,
+    }
+}
+
+fn rewrite_inst(inst: &mut Instruction, copies: &Copies) -> usize {
+    match inst {
+        Instruction::Alloc { num: op, .. } |
+        Instruction::Copy { op, .. } => operand(op, copies),
+        Instruction::Gep { src, idx, .. } => variable(src, copies) + operand(idx, copies),
+        Instruction::Arith { op1, op2, .. } |
+        Instruction::Cmp { op1, op2, .. } => operand(op1, copies) + operand(op2, copies),
+        Instruction::Load { src, .. } |
+        Instruction::Gfp { src, .. } => variable(src, copies),
+        Instruction::Store { dst, op } => variable(dst, copies) + operand(op, copies),
+        Instruction::CallExt { args, .. } => args.iter_mut().map(|a| operand(a, copies)).sum(),
+        // the address of x is not the address of the variable x was copied from
+        Instruction::AddrOf { .. } => 0,
+    }
+}
+
+fn rewrite_term(term: &mut Terminal, copies: &Copies) -> usize {
+    match term {
+        Terminal::Branch { cond: op, .. } |
+        Terminal::Ret(Some(op)) => operand(op, copies),
+        Terminal::CallDirect { args, .. } => args.iter_mut().map(|a| operand(a, copies)).sum(),
+        Terminal::CallIndirect { callee, args, .. } =>
+            variable(callee, copies) + args.iter_mut().map(|a| operand(a, copies)).sum::<usize>(),
+        Terminal::Jump(_) |
+        Terminal::Ret(None) => 0,
+    }
+}
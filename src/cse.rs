@@ -0,0 +1,98 @@
+use crate::available::{self, Facts};
+use crate::lir::{*};
+use crate::reaching::{Effects, Memory, Site};
+
+// common subexpression elimination over available expressions: once lhs = $arith, $cmp, $gfp or
+// $gep has computed an expression, a later computation of the same expression becomes a $copy of
+// lhs, as long as neither lhs nor any variable the expression reads may have been written since.
+// expressions are compared as written, so a + b and b + a are different expressions.
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Expr {
+    // the instruction without its lhs, e.g. "$arith add x 1"
+    text: String,
+    vars: Vec<String>,
+}
+
+// each available expression and the variable holding its value
+type Available = Facts<Expr, Variable>;
+
+fn op_var(op: &Operand) -> Option<String> {
+    match op {
+        Operand::Var(v) => Some(v.name.clone()),
+        Operand::CInt(_) => None,
+    }
+}
+
+// the expression inst computes and the variable it assigns, for the instructions cse handles
+fn expression(inst: &Instruction) -> Option<(Expr, &Variable)> {
+    let (text, vars) = match inst {
+        Instruction::Arith { aop, op1, op2, .. } =>
+            (format!("$arith {} {} {}", aop, op1, op2), [op_var(op1), op_var(op2)].into_iter().flatten().collect()),
+        Instruction::Cmp { rop, op1, op2, .. } =>
+            (format!("$cmp {} {} {}", rop, op1, op2), [op_var(op1), op_var(op2)].into_iter().flatten().collect()),
+        Instruction::Gep { src, idx, .. } =>
+            (format!("$gep {} {}", src.name, idx), [Some(src.name.clone()), op_var(idx)].into_iter().flatten().collect()),
+        Instruction::Gfp { src, field, .. } => (format!("$gfp {} {}", src.name, field.name), vec![src.name.clone()]),
+        _ => return None,
+    };
+    let lhs = match inst {
+        Instruction::Arith { lhs, .. } |
+        Instruction::Cmp { lhs, .. } |
+        Instruction::Gep { lhs, .. } |
+        Instruction::Gfp { lhs, .. } => lhs,
+        _ => unreachable!(),
+    };
+    Some((Expr { text, vars }, lhs))
+}
+
+pub fn eliminate(program: &Program) -> (Program, usize) {
+    let mut result = program.clone();
+    let mut replaced = 0;
+    for name in program.functions.keys() {
+        let (function, n) = eliminate_function(program, name);
+        result.functions.insert(name.clone(), function);
+        replaced += n;
+    }
+    (result, replaced)
+}
+
+// the function with redundant computations turned into copies, and how many there were
+pub fn eliminate_function(program: &Program, function_name: &str) -> (Function, usize) {
+    let mut function = program.functions[function_name].clone();
+    let memory = Memory::new(program, &function);
+    let starts = available::solve(&function, |available: &mut Available, site| transfer(available, site, &memory));
+
+    let mut replaced = 0;
+    for (bb_name, bb) in function.body.iter_mut() {
+        let mut available = match starts.get(bb_name) {
+            Some(available) => available.clone(),
+            None => continue,
+        };
+        for inst in bb.insts.iter_mut() {
+            let holder = expression(inst)
+                .and_then(|(expr, lhs)| available.get(&expr).filter(|holder| holder.name != lhs.name).map(|h| (h.clone(), lhs.clone())));
+            // the transfer of the original instruction, so that the facts match the solution
+            transfer(&mut available, Site::Inst(inst), &memory);
+            if let Some((holder, lhs)) = holder {
+                *inst = Instruction::Copy { lhs, op: Operand::Var(holder) };
+                replaced += 1;
+            }
+        }
+    }
+    (function, replaced)
+}
+
+fn transfer(available: &mut Available, site: Site, memory: &Memory) {
+    let effects = Effects::of_site(site, memory);
+    for var in effects.strong_defs.iter().chain(effects.weak_defs.iter()) {
+        available.retain(|expr, holder| holder.name != *var && !expr.vars.contains(var));
+    }
+    if let Site::Inst(inst) = site {
+        if let Some((expr, lhs)) = expression(inst) {
+            if !expr.vars.contains(&lhs.name) && !available.contains_key(&expr) {
+                available.insert(expr, lhs.clone());
+            }
+        }
+    }
+}
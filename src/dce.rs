@@ -0,0 +1,226 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use crate::cfg::{self, Cfg};
+use crate::lir::{*};
+use crate::liveness::Liveness;
+use crate::reaching::ProgramPoint;
+
+// dead code elimination: blocks unreachable from entry are deleted, assignments to locals that are
+// not live afterwards are deleted when they have no effect besides the assignment, locals nothing
+// mentions any more are dropped, and straight-line $jump chains are merged into single blocks.
+// loads and divisions by anything but a non-zero constant are kept even when dead, as they may fault.
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DceReport {
+    // "function.block"
+    pub unreachable_blocks: Vec<String>,
+    // "function.block: instruction", with the block as it was when the instruction was removed
+    pub dead_instructions: Vec<String>,
+    // "function.local"
+    pub unused_locals: Vec<String>,
+    // "function.block" merged into the block that jumped to it, or bypassed when it only jumped on
+    pub merged_blocks: Vec<String>,
+}
+
+impl DceReport {
+    pub fn removed(&self) -> usize {
+        self.unreachable_blocks.len() + self.dead_instructions.len() + self.unused_locals.len() + self.merged_blocks.len()
+    }
+
+    fn extend(&mut self, other: DceReport) {
+        self.unreachable_blocks.extend(other.unreachable_blocks);
+        self.dead_instructions.extend(other.dead_instructions);
+        self.unused_locals.extend(other.unused_locals);
+        self.merged_blocks.extend(other.merged_blocks);
+    }
+}
+
+impl fmt::Display for DceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.unreachable_blocks.iter().try_for_each(|b| writeln!(f, "unreachable block {}", b))?;
+        self.dead_instructions.iter().try_for_each(|i| writeln!(f, "dead instruction {}", i))?;
+        self.unused_locals.iter().try_for_each(|l| writeln!(f, "unused local {}", l))?;
+        self.merged_blocks.iter().try_for_each(|b| writeln!(f, "merged block {}", b))
+    }
+}
+
+pub fn eliminate(program: &Program) -> (Program, DceReport) {
+    let mut result = program.clone();
+    let mut report = DceReport::default();
+    let mut names: Vec<&String> = program.functions.keys().collect();
+    names.sort();
+    for name in names {
+        let (function, function_report) = eliminate_function(program, name);
+        result.functions.insert(name.clone(), function);
+        report.extend(function_report);
+    }
+    (result, report)
+}
+
+pub fn eliminate_function(program: &Program, function_name: &str) -> (Function, DceReport) {
+    let mut function = program.functions[function_name].clone();
+    let mut report = DceReport::default();
+    remove_unreachable(&mut function, &mut report);
+    // removing an assignment can make the ones feeding it dead
+    while remove_dead(program, &mut function, &mut report) {}
+    merge_jumps(&mut function, &mut report);
+    remove_unused_locals(&mut function, &mut report);
+    (function, report)
+}
+
+fn sorted<'a>(names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut names: Vec<String> = names.cloned().collect();
+    names.sort();
+    names
+}
+
+fn remove_unreachable(function: &mut Function, report: &mut DceReport) {
+    let reachable: HashSet<String> = Cfg::new(function).rpo.into_iter().collect();
+    for bb in sorted(function.body.keys()) {
+        if !reachable.contains(&bb) {
+            function.body.remove(&bb);
+            report.unreachable_blocks.push(format!("{}.{}", function.id, bb));
+        }
+    }
+}
+
+// the variable inst assigns, if removing inst when that variable is dead changes nothing else
+fn removable_def(inst: &Instruction) -> Option<&Variable> {
+    match inst {
+        Instruction::Arith { aop: ArithOp::Divide, op2, .. } if !matches!(op2, Operand::CInt(c) if *c != 0) => None,
+        Instruction::AddrOf { lhs, .. } |
+        Instruction::Alloc { lhs, .. } |
+        Instruction::Copy { lhs, .. } |
+        Instruction::Gep { lhs, .. } |
+        Instruction::Arith { lhs, .. } |
+        Instruction::Gfp { lhs, .. } |
+        Instruction::Cmp { lhs, .. } => Some(lhs),
+        Instruction::Load { .. } |
+        Instruction::Store { .. } |
+        Instruction::CallExt { .. } => None,
+    }
+}
+
+fn remove_dead(program: &Program, function: &mut Function, report: &mut DceReport) -> bool {
+    let locals: HashSet<String> = function.locals.iter().chain(function.params.iter()).map(|v| v.name.clone()).collect();
+    // liveness is computed on the program as given, with this function in its current state
+    let mut current = program.clone();
+    current.functions.insert(function.id.clone(), function.clone());
+    let liveness = Liveness::new(&current, &current.functions[&function.id]);
+
+    let mut removed = false;
+    for bb_name in sorted(function.body.keys()) {
+        let bb = function.body.get_mut(&bb_name).unwrap();
+        let mut kept = vec![];
+        for (index, inst) in bb.insts.drain(..).enumerate() {
+            let point = ProgramPoint::new(&function.id, &bb_name, index);
+            match removable_def(&inst) {
+                Some(lhs) if locals.contains(&lhs.name) && !liveness.is_live_after(&point, &lhs.name) => {
+                    report.dead_instructions.push(format!("{}.{}: {}", function.id, bb_name, inst));
+                    removed = true;
+                }
+                _ => kept.push(inst),
+            }
+        }
+        bb.insts = kept;
+    }
+    removed
+}
+
+fn predecessors(function: &Function) -> HashMap<String, Vec<String>> {
+    let mut preds: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, bb) in function.body.iter() {
+        let mut targets = cfg::successors(&bb.term);
+        targets.dedup();
+        targets.into_iter().for_each(|t| preds.entry(t).or_default().push(name.clone()));
+    }
+    preds
+}
+
+// a block whose only predecessor jumps to it is appended to that predecessor, and an empty block
+// that only jumps on is bypassed by everything that reaches it
+fn merge_jumps(function: &mut Function, report: &mut DceReport) {
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let preds = predecessors(function);
+        for name in sorted(function.body.keys()) {
+            if name == "entry" || !function.body.contains_key(&name) {
+                continue;
+            }
+            let target = match &function.body[&name].term {
+                Terminal::Jump(target) if *target != name => Some(target.clone()),
+                _ => None,
+            };
+            let preds_of = preds.get(&name).cloned().unwrap_or_default();
+            if let [pred] = &preds_of[..] {
+                if *pred != name && matches!(&function.body[pred].term, Terminal::Jump(t) if *t == name) {
+                    let bb = function.body.remove(&name).unwrap();
+                    let pred_bb = function.body.get_mut(pred).unwrap();
+                    pred_bb.insts.extend(bb.insts);
+                    pred_bb.term = bb.term;
+                    report.merged_blocks.push(format!("{}.{}", function.id, name));
+                    changed = true;
+                    break;
+                }
+            }
+            if let Some(target) = target.filter(|_| function.body[&name].insts.is_empty()) {
+                for pred in preds_of.iter() {
+                    cfg::retarget(&mut function.body.get_mut(pred).unwrap().term, &name, &target);
+                }
+                function.body.remove(&name);
+                report.merged_blocks.push(format!("{}.{}", function.id, name));
+                changed = true;
+                break;
+            }
+        }
+    }
+}
+
+fn mentioned(function: &Function) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut add = |op: &Operand| if let Operand::Var(v) = op { names.insert(v.name.clone()); };
+    let mut vars: Vec<&Variable> = vec![];
+    for bb in function.body.values() {
+        for inst in bb.insts.iter() {
+            match inst {
+                Instruction::AddrOf { lhs, rhs } => vars.extend([lhs, rhs]),
+                Instruction::Alloc { lhs, num, .. } => { vars.push(lhs); add(num); }
+                Instruction::Copy { lhs, op } => { vars.push(lhs); add(op); }
+                Instruction::Gep { lhs, src, idx } => { vars.extend([lhs, src]); add(idx); }
+                Instruction::Arith { lhs, op1, op2, .. } |
+                Instruction::Cmp { lhs, op1, op2, .. } => { vars.push(lhs); add(op1); add(op2); }
+                Instruction::Load { lhs, src } |
+                Instruction::Gfp { lhs, src, .. } => vars.extend([lhs, src]),
+                Instruction::Store { dst, op } => { vars.push(dst); add(op); }
+                Instruction::CallExt { lhs, args, .. } => { vars.extend(lhs.iter()); args.iter().for_each(&mut add); }
+            }
+        }
+        match &bb.term {
+            Terminal::Branch { cond, .. } => add(cond),
+            Terminal::Ret(Some(op)) => add(op),
+            Terminal::CallDirect { lhs, args, .. } => { vars.extend(lhs.iter()); args.iter().for_each(&mut add); }
+            Terminal::CallIndirect { lhs, callee, args, .. } => {
+                vars.extend(lhs.iter());
+                vars.push(callee);
+                args.iter().for_each(&mut add);
+            }
+            Terminal::Jump(_) |
+            Terminal::Ret(None) => {}
+        }
+    }
+    names.extend(vars.into_iter().map(|v| v.name.clone()));
+    names
+}
+
+fn remove_unused_locals(function: &mut Function, report: &mut DceReport) {
+    let mentioned = mentioned(function);
+    let id = function.id.clone();
+    function.locals.retain(|local| {
+        let used = mentioned.contains(&local.name);
+        if !used {
+            report.unused_locals.push(format!("{}.{}", id, local.name));
+        }
+        used
+    });
+}
@@ -0,0 +1,97 @@
+use std::collections::{BTreeMap, BTreeSet};
+use crate::lir::{*};
+use crate::reaching::{*};
+
+// use-def and def-use chains of a function, derived from reaching definitions.
+// a use is a (program point, variable) pair read by an instruction or terminal; it is linked to
+// every definition of that variable reaching the point. values read from params, globals or
+// uninitialized locals have no defining program point and so have no chain.
+#[derive(Debug, Default)]
+pub struct DefUse {
+    // use point -> variable -> definitions reaching that use
+    use_def: BTreeMap<ProgramPoint, BTreeMap<String, BTreeSet<ProgramPoint>>>,
+    // definition point -> variable -> uses it may feed
+    def_use: BTreeMap<ProgramPoint, BTreeMap<String, BTreeSet<ProgramPoint>>>,
+    // reaching definitions of every variable at the start of each reachable block
+    block_entry: BTreeMap<String, RdefStore>,
+    // blocks processed by the reaching definitions worklist
+    pub iterations: usize,
+}
+
+impl DefUse {
+    pub fn new(program: &Program, function_name: &str) -> DefUse {
+        let function = program.functions.get(function_name).unwrap();
+        let memory = Memory::new(program, function);
+        let (point2store, iterations) = reaching_defs(program, function_name);
+
+        let mut chains = DefUse { iterations, ..DefUse::default() };
+        for (point, store) in point2store.iter() {
+            if point.index == 0 {
+                chains.block_entry.insert(point.block.clone(), store.clone());
+            }
+            let effects = Effects::of_site(point.site(function).unwrap(), &memory);
+            // every definition point is present, even when nothing reads it
+            if !effects.strong_defs.is_empty() || !effects.weak_defs.is_empty() {
+                chains.def_use.entry(point.clone()).or_default();
+            }
+            for var in effects.uses.iter() {
+                let defs = match store.get(var) {
+                    Some(defs) if !defs.is_empty() => defs,
+                    _ => continue,
+                };
+                chains.use_def.entry(point.clone()).or_default().insert(var.clone(), defs.clone());
+                for def in defs {
+                    chains.def_use.entry(def.clone()).or_default()
+                        .entry(var.clone()).or_default()
+                        .insert(point.clone());
+                }
+            }
+        }
+        chains
+    }
+
+    // definitions of var that reach the use at point
+    pub fn reaching(&self, point: &ProgramPoint, var: &str) -> impl Iterator<Item = &ProgramPoint> {
+        self.use_def.get(point).and_then(|vars| vars.get(var)).into_iter().flatten()
+    }
+
+    // uses of var that the definition at point may feed
+    pub fn uses_of(&self, point: &ProgramPoint, var: &str) -> impl Iterator<Item = &ProgramPoint> {
+        self.def_use.get(point).and_then(|vars| vars.get(var)).into_iter().flatten()
+    }
+
+    // all variables read at point together with their reaching definitions
+    pub fn defs_at(&self, point: &ProgramPoint) -> impl Iterator<Item = (&String, &BTreeSet<ProgramPoint>)> {
+        self.use_def.get(point).into_iter().flatten()
+    }
+
+    // all variables written at point together with the uses they feed
+    pub fn uses_at(&self, point: &ProgramPoint) -> impl Iterator<Item = (&String, &BTreeSet<ProgramPoint>)> {
+        self.def_use.get(point).into_iter().flatten()
+    }
+
+    // definitions of every variable reaching the start of a block
+    pub fn block_entry(&self, bb: &str) -> Option<&RdefStore> {
+        self.block_entry.get(bb)
+    }
+
+    // program points reading at least one variable with a reaching definition, in order
+    pub fn use_points(&self) -> impl Iterator<Item = &ProgramPoint> {
+        self.use_def.keys()
+    }
+
+    // program points defining at least one variable, in order
+    pub fn def_points(&self) -> impl Iterator<Item = &ProgramPoint> {
+        self.def_use.keys()
+    }
+
+    // every definition reaching point, regardless of the variable
+    pub fn all_reaching(&self, point: &ProgramPoint) -> BTreeSet<&ProgramPoint> {
+        self.defs_at(point).flat_map(|(_, defs)| defs.iter()).collect()
+    }
+
+    // every use fed by the definition at point, regardless of the variable
+    pub fn all_uses(&self, point: &ProgramPoint) -> BTreeSet<&ProgramPoint> {
+        self.uses_at(point).flat_map(|(_, uses)| uses.iter()).collect()
+    }
+}
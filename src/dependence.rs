@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use crate::lir::{*};
+use crate::cfg::{Cfg, EXIT};
+use crate::reaching::ProgramPoint;
+use crate::defuse::DefUse;
+use crate::dot::escape;
+
+// id of the region node that every point outside of any branch is control dependent on
+pub const PDG_ENTRY: &str = "ENTRY";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DepKind {
+    Data,
+    Control,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PdgNode {
+    pub id: String,
+    pub block: String,
+    pub index: usize,
+    pub label: String,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PdgEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: DepKind,
+    // the variable carried by a data edge, or the branch outcome ("true"/"false") of a control edge
+    pub label: String,
+}
+
+// program dependence graph of one function; nodes are the program points of reachable blocks
+#[derive(Serialize, Debug)]
+pub struct Pdg {
+    pub function: String,
+    pub nodes: Vec<PdgNode>,
+    pub edges: Vec<PdgEdge>,
+}
+
+impl Pdg {
+    pub fn new(program: &Program, function_name: &str) -> Pdg {
+        let function = program.functions.get(function_name).unwrap();
+        let cfg = Cfg::new(function);
+        let chains = DefUse::new(program, function_name);
+
+        let mut nodes = vec![PdgNode {
+            id: PDG_ENTRY.to_string(),
+            block: cfg.entry.clone(),
+            index: 0,
+            label: "entry".to_string(),
+        }];
+        let mut blocks = cfg.rpo.clone();
+        blocks.sort();
+        for bb in blocks.iter() {
+            for index in 0..=function.body[bb].insts.len() {
+                let point = ProgramPoint::new(function_name, bb, index);
+                nodes.push(PdgNode {
+                    id: point.to_string(),
+                    block: bb.clone(),
+                    index,
+                    label: point.site(function).unwrap().to_string(),
+                });
+            }
+        }
+
+        let mut edges = vec![];
+        for point in chains.use_points() {
+            for (var, defs) in chains.defs_at(point) {
+                edges.extend(defs.iter().map(|def| PdgEdge {
+                    from: def.to_string(),
+                    to: point.to_string(),
+                    kind: DepKind::Data,
+                    label: var.clone(),
+                }));
+            }
+        }
+
+        // control edges go from the branch terminal to every point of the dependent block
+        let mut deps: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+        for (branch, taken, bb) in cfg.control_dep_edges() {
+            let label = match &function.body[&branch].term {
+                Terminal::Branch { tt, .. } if *tt == taken => "true",
+                _ => "false",
+            };
+            let from = ProgramPoint::new(function_name, &branch, function.body[&branch].insts.len());
+            deps.entry(bb).or_default().push((from.to_string(), label.to_string()));
+        }
+        // the blocks on the post-dominator chain of entry run whenever the function is called
+        let ipdom = cfg.post_dominators();
+        let mut runner = Some(&cfg.entry);
+        while let Some(bb) = runner {
+            if bb == EXIT { break; }
+            deps.entry(bb.clone()).or_default().push((PDG_ENTRY.to_string(), String::new()));
+            runner = ipdom.get(bb);
+        }
+        for bb in blocks.iter() {
+            // blocks that cannot reach a return may have no dependence left; hang them off ENTRY
+            let sources = deps.remove(bb)
+                .unwrap_or_else(|| vec![(PDG_ENTRY.to_string(), String::new())]);
+            for index in 0..=function.body[bb].insts.len() {
+                let to = ProgramPoint::new(function_name, bb, index).to_string();
+                edges.extend(sources.iter().map(|(from, label)| PdgEdge {
+                    from: from.clone(),
+                    to: to.clone(),
+                    kind: DepKind::Control,
+                    label: label.clone(),
+                }));
+            }
+        }
+        edges.sort();
+        edges.dedup();
+
+        Pdg { function: function_name.to_string(), nodes, edges }
+    }
+
+    pub fn as_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    // graphviz rendering: solid edges are control dependences, dashed ones data dependences
+    pub fn as_dot(&self) -> String {
+        let mut dot = format!("digraph \"pdg_{}\" {{\n", escape(&self.function));
+        dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+        for node in self.nodes.iter() {
+            let label = if node.id == PDG_ENTRY {
+                node.label.clone()
+            } else {
+                format!("{}: {}", node.id, node.label)
+            };
+            dot.push_str(&format!("  \"{}\" [label=\"{}\"];\n", escape(&node.id), escape(&label)));
+        }
+        for edge in self.edges.iter() {
+            let style = match edge.kind {
+                DepKind::Data => "dashed",
+                DepKind::Control => "solid",
+            };
+            dot.push_str(&format!("  \"{}\" -> \"{}\" [style={}, label=\"{}\"];\n",
+                                  escape(&edge.from), escape(&edge.to), style, escape(&edge.label)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::cfg::Cfg;
+use crate::dot;
+use crate::lir::{*};
+use crate::report::{Fact, OutputFormat, Report};
+
+// the worklist of the non-relational integer analyses, generic over the abstract value of a
+// variable. an analysis supplies the lattice and the transfer of arithmetic and comparisons; the
+// handling of copies, memory, calls and branches, and the printing of the stores, are shared.
+
+pub trait AbstractValue: Copy + PartialEq {
+    fn top() -> Self;
+    fn bottom() -> Self;
+    fn constant(value: i32) -> Self;
+    fn join(a: &Self, b: &Self) -> Self;
+    // old joined with new, giving up enough precision that every increasing chain is finite.
+    // lattices of finite height need nothing beyond the join
+    fn widen(old: &Self, new: &Self) -> Self {
+        Self::join(old, new)
+    }
+    fn arith(op1: &Self, op2: &Self, aop: &ArithOp) -> Self;
+    fn cmp(op1: &Self, op2: &Self, rop: &RelaOp) -> Self;
+    // whether a branch on the value may take the true (non-zero) and the false (zero) edge
+    fn truth(&self) -> (bool, bool);
+    fn as_string(&self) -> String;
+}
+
+#[derive(Clone, Debug)]
+pub struct AbstractStore<V> {
+    store: HashMap<String, V>,
+}
+
+impl<V: AbstractValue> AbstractStore<V> {
+    fn new() -> AbstractStore<V> {
+        AbstractStore {
+            store: HashMap::new(),
+        }
+    }
+
+    // every variable of the store with its abstract value
+    pub fn values(&self) -> impl Iterator<Item = (&String, &V)> {
+        self.store.iter()
+    }
+
+    fn insert(&mut self, var: String, value: V) {
+        self.store.insert(var, value);
+    }
+
+    // change self according to join, return true if self is changed
+    fn join(&mut self, store_to_join: &AbstractStore<V>) -> bool {
+        self.merge(store_to_join, false)
+    }
+
+    // like join, widening every value instead
+    fn widen(&mut self, store_to_join: &AbstractStore<V>) -> bool {
+        self.merge(store_to_join, true)
+    }
+
+    fn merge(&mut self, store_to_join: &AbstractStore<V>, widen: bool) -> bool {
+        let mut changed = false;
+        for (var, value_to_join) in &store_to_join.store {
+            let old_value = self.store.entry(var.clone()).or_insert(V::bottom());
+            let joined = V::join(value_to_join, old_value);
+            let new_value = if widen { V::widen(old_value, &joined) } else { joined };
+            if new_value != *old_value {
+                *old_value = new_value;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // the abstract value of operand in this store; int variables it does not track are bottom, and
+    // any other variable, e.g. a pointer compared with another, may be anything
+    pub fn resolve_operand(&self, operand: &Operand) -> V {
+        match operand {
+            Operand::Var(v) if v.typ != Type::Int => V::top(),
+            Operand::Var(v) => self.store.get(&v.name).cloned().unwrap_or(V::bottom()),
+            Operand::CInt(i) => V::constant(*i),
+        }
+    }
+
+    fn execute(&mut self, inst: &Instruction, globals: &Globals) {
+        match inst {
+            Instruction::Copy { lhs, op } => {
+                if lhs.typ != Type::Int { return; }
+                let op = self.resolve_operand(op);
+                self.insert(lhs.name.clone(), op);
+            }
+            Instruction::Arith { lhs, op1, op2, aop } => {
+                let op1 = self.resolve_operand(op1);
+                let op2 = self.resolve_operand(op2);
+                self.insert(lhs.name.clone(), V::arith(&op1, &op2, aop));
+            }
+            Instruction::Cmp { lhs, op1, op2, rop } => {
+                let op1 = self.resolve_operand(op1);
+                let op2 = self.resolve_operand(op2);
+                self.insert(lhs.name.clone(), V::cmp(&op1, &op2, rop));
+            }
+            Instruction::Load { lhs, .. } => {
+                if lhs.typ != Type::Int { return; }
+                self.insert(lhs.name.clone(), V::top());
+            }
+            Instruction::Store { dst, op } => {
+                // a store through a pointer to int may write any address-taken int
+                if !dst.typ.is_pointer_to_int() { return; }
+                let mut tmp_store = AbstractStore::new();
+                let op = self.resolve_operand(op);
+                globals.addr_taken_ints.iter().for_each(|v| tmp_store.insert(v.name.clone(), op));
+                self.join(&tmp_store);
+            }
+            Instruction::CallExt { lhs, args, .. } => self.call(lhs, args, globals),
+            Instruction::AddrOf { .. } |
+            Instruction::Alloc { .. } |
+            Instruction::Gep { .. } |
+            Instruction::Gfp { .. } => {}
+        }
+    }
+
+    fn call(&mut self, lhs: &Option<Variable>, args: &[Operand], globals: &Globals) {
+        globals.global_ints.iter().for_each(|v| self.insert(v.name.clone(), V::top()));
+        if let Some(lhs) = lhs {
+            if lhs.typ == Type::Int {
+                self.insert(lhs.name.clone(), V::top());
+            }
+        }
+        let any_arg_reaches_int = args.iter().any(|arg| matches!(arg, Operand::Var(v) if v.typ.is_pointer_to_int()));
+        if any_arg_reaches_int || globals.global_ptr_to_ints {
+            globals.addr_taken_ints.iter().for_each(|v| self.insert(v.name.clone(), V::top()));
+        }
+    }
+}
+
+// the store of every block, by name
+pub type Stores<V> = HashMap<String, AbstractStore<V>>;
+
+// facts about the whole program that calls and stores need, computed once per analyzed function
+pub struct Globals {
+    pub global_ints: Vec<Variable>,
+    // ints whose address the function takes, which stores through int pointers may write
+    pub addr_taken_ints: Vec<Variable>,
+    // whether a global may lead to an int, so that calls may write address-taken ints
+    pub global_ptr_to_ints: bool,
+}
+
+impl Globals {
+    pub fn new(program: &Program, function_name: &str) -> Globals {
+        let global_ints = program.globals.iter().filter(|g| g.typ == Type::Int).cloned().collect();
+        let mut addr_taken_ints: Vec<Variable> = program.functions[function_name].body.values()
+            .flat_map(|bb| bb.insts.iter())
+            .filter_map(|inst| match inst {
+                Instruction::AddrOf { rhs, .. } if rhs.typ == Type::Int => Some(rhs.clone()),
+                _ => None,
+            })
+            .collect();
+        addr_taken_ints.sort();
+        addr_taken_ints.dedup();
+        let global_ptr_to_ints = program.globals.iter().any(|g| g.typ.is_pointer_to_int());
+        Globals { global_ints, addr_taken_ints, global_ptr_to_ints }
+    }
+}
+
+// the entry and exit stores of every processed block, and the number of blocks processed. int
+// locals start out unset, so reading one before it is written is undefined, unless zero_locals
+// starts them at the zero the interpreter gives them
+pub fn solve<V: AbstractValue>(program: &Program, function_name: &str, globals: &Globals,
+                               zero_locals: bool) -> (Stores<V>, Stores<V>, usize) {
+    let function = program.functions.get(function_name).unwrap();
+    let mut bb2store: Stores<V> = HashMap::new();
+    let mut bb2store_post: Stores<V> = HashMap::new();
+    let mut working_list = VecDeque::new();
+    let mut iterations = 0;
+    working_list.push_back("entry".to_string());
+    let widen_at = Cfg::new(function).widening_points();
+
+    // global ints and int parameters may hold anything on entry
+    let mut initial_store = AbstractStore::new();
+    program.globals.iter().chain(function.params.iter())
+        .filter(|v| v.typ == Type::Int)
+        .for_each(|v| initial_store.insert(v.name.clone(), V::top()));
+    if zero_locals {
+        function.locals.iter()
+            .filter(|v| v.typ == Type::Int)
+            .for_each(|v| initial_store.insert(v.name.clone(), V::constant(0)));
+    }
+    bb2store.insert("entry".to_string(), initial_store);
+
+    while let Some(bb_name) = working_list.pop_front() {
+        iterations += 1;
+        let bb = function.body.get(&bb_name).unwrap();
+        let mut current_store = bb2store.entry(bb_name.clone()).or_insert_with(AbstractStore::new).clone();
+        bb.insts.iter().for_each(|inst| current_store.execute(inst, globals));
+
+        let target_bb: Vec<String> = match &bb.term {
+            Terminal::Branch { tt, ff, cond } => {
+                let (may_be_true, may_be_false) = current_store.resolve_operand(cond).truth();
+                let mut targets = vec![];
+                if may_be_true { targets.push(tt.clone()); }
+                if may_be_false { targets.push(ff.clone()); }
+                targets
+            }
+            Terminal::Jump(target) => vec![target.clone()],
+            Terminal::CallDirect { next_bb, lhs, args, .. } |
+            Terminal::CallIndirect { next_bb, lhs, args, .. } => {
+                current_store.call(lhs, args, globals);
+                vec![next_bb.clone()]
+            }
+            Terminal::Ret(_) => vec![],
+        };
+
+        bb2store_post.insert(bb_name.clone(), current_store.clone());
+
+        // a target whose store changed is (re)processed, and so is a target reached for the first
+        // time even when the join changed nothing, e.g. with an empty store
+        for target in target_bb.iter() {
+            let target_store = bb2store.entry(target.clone()).or_insert_with(AbstractStore::new);
+            let changed = if widen_at.contains(target) {
+                target_store.widen(&current_store)
+            } else {
+                target_store.join(&current_store)
+            };
+            if changed || !bb2store_post.contains_key(target) && !working_list.contains(target) {
+                working_list.push_back(target.clone());
+            }
+        }
+    }
+
+    (bb2store, bb2store_post, iterations)
+}
+
+// the exit store of every block the analysis reached, and the number of blocks processed
+pub fn analyze<V: AbstractValue>(program: &Program, function_name: &str) -> (Stores<V>, usize) {
+    let globals = Globals::new(program, function_name);
+    let (_, bb2store_post, iterations) = solve(program, function_name, &globals, false);
+    (bb2store_post, iterations)
+}
+
+// the store just before every instruction of every block the worklist reached, by block, and last
+// the one before its terminal. these are for transforms and checks of what the program does, so
+// int locals start at zero as in the interpreter
+pub fn points<V: AbstractValue>(program: &Program, function_name: &str) -> HashMap<String, Vec<AbstractStore<V>>> {
+    let globals = Globals::new(program, function_name);
+    let (bb2store, bb2store_post, _) = solve::<V>(program, function_name, &globals, true);
+    let function = program.functions.get(function_name).unwrap();
+    bb2store.iter()
+        .filter(|(block, _)| bb2store_post.contains_key(*block))
+        .map(|(block, store)| {
+            let mut current_store = store.clone();
+            let mut stores = vec![current_store.clone()];
+            function.body[block].insts.iter().for_each(|inst| {
+                current_store.execute(inst, &globals);
+                stores.push(current_store.clone());
+            });
+            (block.clone(), stores)
+        })
+        .collect()
+}
+
+// the non-bottom entries of every block's store as "var -> val" lines, sorted by variable
+pub fn store_lines<V: AbstractValue>(store: &Stores<V>) -> HashMap<String, Vec<String>> {
+    store.iter().map(|(block, abs_store)| {
+        let mut keys: Vec<&String> = abs_store.store.keys().collect();
+        keys.sort();
+        let lines = keys.iter()
+            .filter(|key| abs_store.store[**key] != V::bottom())
+            .map(|key| format!("{} -> {}", key, abs_store.store[*key].as_string()))
+            .collect();
+        (block.clone(), lines)
+    }).collect()
+}
+
+pub fn store_report<V: AbstractValue>(analysis: &str, function_name: &str, store: &Stores<V>,
+                                      iterations: usize) -> Report {
+    let mut report = Report::new(analysis, function_name, Some(iterations));
+    report.blocks = store.iter().map(|(block, abs_store)| {
+        let facts: BTreeMap<String, Fact> = abs_store.store.iter()
+            .filter(|(_, val)| **val != V::bottom())
+            .map(|(key, val)| (key.clone(), Fact::Value(val.as_string())))
+            .collect();
+        (block.clone(), facts)
+    }).collect();
+    report
+}
+
+pub fn format_store<V: AbstractValue>(store: &Stores<V>) -> String {
+    let lines = store_lines(store);
+    let mut blocks: Vec<&String> = lines.keys().collect();
+    blocks.sort();
+    let mut output = String::new();
+    for block in blocks {
+        output.push_str(&format!("{}:\n", block));
+        lines[block].iter().for_each(|line| output.push_str(&format!("{}\n", line)));
+        output.push('\n');
+    }
+    output
+}
+
+// run the analysis named analysis with values V on one function and render the result
+pub fn render<V: AbstractValue>(analysis: &str, program: &Program, function_name: &str, format: OutputFormat) -> String {
+    let (store, iterations) = analyze::<V>(program, function_name);
+    match format {
+        OutputFormat::Text => format_store(&store),
+        OutputFormat::Json => format!("{}\n", store_report(analysis, function_name, &store, iterations).as_json()),
+        OutputFormat::Dot => {
+            let function = program.functions.get(function_name).unwrap();
+            dot::function_to_dot(function, &store_lines(&store))
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use crate::lir::{*};
+
+pub fn escape(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// graphviz rendering of a function's cfg. every block becomes a box listing its instructions and
+// terminal, followed by the analysis facts given for it; blocks without facts were never reached
+// by the analysis and are drawn grey.
+pub fn function_to_dot(function: &Function, facts: &HashMap<String, Vec<String>>) -> String {
+    let mut dot = format!("digraph \"{}\" {{\n", escape(&function.id));
+    dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+
+    let mut blocks: Vec<&String> = function.body.keys().collect();
+    blocks.sort();
+    for bb_name in blocks.iter() {
+        let bb = &function.body[*bb_name];
+        let mut code = vec![format!("{}:", bb_name)];
+        bb.insts.iter().for_each(|inst| code.push(format!("  {}", inst)));
+        code.push(format!("  {}", bb.term));
+        match facts.get(*bb_name) {
+            Some(lines) => {
+                let mut label = left_justified(&code, record_escape);
+                if !lines.is_empty() {
+                    label = format!("{}|{}", label, left_justified(lines, record_escape));
+                }
+                dot.push_str(&format!("  \"{}\" [shape=record, label=\"{{{}}}\"];\n", escape(bb_name), label));
+            }
+            None => {
+                dot.push_str(&format!("  \"{}\" [label=\"{}\", color=grey, fontcolor=grey];\n",
+                                      escape(bb_name), left_justified(&code, escape)));
+            }
+        }
+    }
+
+    for bb_name in blocks.iter() {
+        let edges: Vec<(&String, &str)> = match &function.body[*bb_name].term {
+            Terminal::Jump(target) => vec![(target, "")],
+            Terminal::Branch { tt, ff, .. } => vec![(tt, "true"), (ff, "false")],
+            Terminal::CallDirect { next_bb, .. } |
+            Terminal::CallIndirect { next_bb, .. } => vec![(next_bb, "call-return")],
+            Terminal::Ret(_) => vec![],
+        };
+        for (target, label) in edges {
+            dot.push_str(&format!("  \"{}\" -> \"{}\" [label=\"{}\"];\n", escape(bb_name), escape(target), label));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+// "\l" ends a left-justified line in a graphviz label
+fn left_justified(lines: &[String], escape_line: fn(&str) -> String) -> String {
+    lines.iter().map(|line| format!("{}\\l", escape_line(line))).collect()
+}
+
+// record labels give {}|<> a meaning of their own
+fn record_escape(label: &str) -> String {
+    let mut escaped = String::new();
+    for c in escape(label).chars() {
+        if matches!(c, '{' | '}' | '|' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use crate::generate::{generate, GenConfig, Rng};
+use crate::cfg;
+use crate::interp::{self, BlockExit};
+use crate::lir::Program;
+use crate::{ConstResults, ConstValue, IntervalValue};
+
+// differential testing of the constant analyses against the interpreter. each generated program is
+// run on random arguments, and every block exit of every activation is compared with what the
+// analyses claim for that block: a variable they call constant must hold that constant, and a block
+// that executed must not be missing from their results. the analyses must also terminate, within
+// a time limit and within the number of iterations the height of their lattice allows.
+
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    pub seed: u64,
+    // programs to generate, with seeds seed, seed + 1, ...
+    pub count: usize,
+    // runs of main per program
+    pub runs: usize,
+    pub fuel: usize,
+    pub timeout: Duration,
+    pub gen: GenConfig,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        FuzzConfig {
+            seed: 0,
+            count: 100,
+            runs: 4,
+            fuel: 100_000,
+            timeout: Duration::from_secs(5),
+            gen: GenConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    // the analysis claims a constant the run contradicts
+    WrongConstant { block: String, var: String, claimed: i32, actual: i32, args: Vec<i32> },
+    // the block executed but the analysis never reached it
+    MissedBlock { block: String, args: Vec<i32> },
+    Panic(String),
+    Timeout,
+    // more worklist iterations than the lattice height allows
+    TooManyIterations { iterations: usize, bound: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub seed: u64,
+    pub analysis: String,
+    pub function: String,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "seed {}: {} on {}: ", self.seed, self.analysis, self.function)?;
+        match &self.problem {
+            Problem::WrongConstant { block, var, claimed, actual, args } =>
+                write!(f, "{} is {} at the end of {}, but {} on main{:?}", var, claimed, block, actual, args),
+            Problem::MissedBlock { block, args } => write!(f, "{} is unreached, but runs on main{:?}", block, args),
+            Problem::Panic(message) => write!(f, "panicked: {}", message),
+            Problem::Timeout => write!(f, "did not finish in time"),
+            Problem::TooManyIterations { iterations, bound } =>
+                write!(f, "took {} iterations, more than the bound {}", iterations, bound),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FuzzReport {
+    pub programs: usize,
+    // runs that finished, and the block exits they checked
+    pub runs: usize,
+    pub block_exits: usize,
+    // runs that ran out of fuel or faulted; their prefix is still checked
+    pub incomplete_runs: usize,
+    pub findings: Vec<Finding>,
+}
+
+impl fmt::Display for FuzzReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.findings.iter().try_for_each(|finding| writeln!(f, "{}", finding))?;
+        writeln!(f, "{} programs, {} runs ({} incomplete), {} block exits checked, {} findings",
+                 self.programs, self.runs, self.incomplete_runs, self.block_exits, self.findings.len())
+    }
+}
+
+pub const ANALYSES: [&str; 3] = ["constants", "intervals", "sccp"];
+
+fn analyze(program: &Program, analysis: &str, function_name: &str) -> ConstResults {
+    let results = match analysis {
+        "constants" => crate::analyze_constants(program, function_name),
+        "intervals" => crate::analyze_intervals(program, function_name).map(|r| {
+            // the same three level lattice under another name
+            let blocks = r.blocks.into_iter()
+                .map(|(bb, store)| (bb, store.into_iter().map(|(var, val)| (var, match val {
+                    IntervalValue::Top => ConstValue::Top,
+                    IntervalValue::Bottom => ConstValue::Bottom,
+                    IntervalValue::IntConst(i) => ConstValue::IntConst(i),
+                })).collect()))
+                .collect();
+            ConstResults { function: r.function, blocks, iterations: r.iterations }
+        }),
+        "sccp" => crate::analyze_sccp(program, function_name),
+        _ => unreachable!(),
+    };
+    results.expect("fuzzed functions exist and have an entry block")
+}
+
+// run one analysis on its own thread, so that a panic or a runaway fixpoint is reported instead of
+// taking the fuzzer down; a timed out thread is left behind
+fn analyze_guarded(program: &Program, analysis: &str, function_name: &str, timeout: Duration)
+                   -> Result<ConstResults, Problem> {
+    let (sender, receiver) = mpsc::channel();
+    let (program, analysis_name, name) = (program.clone(), analysis.to_string(), function_name.to_string());
+    thread::spawn(move || {
+        let result = panic::catch_unwind(|| analyze(&program, &analysis_name, &name));
+        let _ = sender.send(result.map_err(|e| panic_message(&*e)));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(Ok(results)) => Ok(results),
+        Ok(Err(message)) => Err(Problem::Panic(message)),
+        Err(_) => Err(Problem::Timeout),
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+// each variable's value can rise at most twice (bottom, constant, top), and every rise of a block's
+// store can put each of its successors back on the worklist once
+fn iteration_bound(program: &Program, function_name: &str) -> usize {
+    let function = &program.functions[function_name];
+    let vars = program.globals.len() + function.params.len() + function.locals.len();
+    let edges: usize = function.body.values().map(|bb| cfg::successors(&bb.term).len()).sum();
+    function.body.len() + edges * (2 * vars + 1)
+}
+
+pub fn check_program(program: &Program, seed: u64, config: &FuzzConfig, report: &mut FuzzReport) {
+    report.programs += 1;
+    let mut names: Vec<&String> = program.functions.keys().collect();
+    names.sort();
+
+    // analysis -> function -> results, for the analyses that finished
+    let mut results: BTreeMap<&str, BTreeMap<&str, ConstResults>> = BTreeMap::new();
+    for analysis in ANALYSES.iter() {
+        for name in names.iter() {
+            let finding = |problem| Finding { seed, analysis: analysis.to_string(), function: name.to_string(), problem };
+            match analyze_guarded(program, analysis, name, config.timeout) {
+                Ok(r) => {
+                    let bound = iteration_bound(program, name);
+                    // sccp counts its steps over ssa edges rather than blocks
+                    if *analysis != "sccp" && r.iterations > bound {
+                        report.findings.push(finding(Problem::TooManyIterations { iterations: r.iterations, bound }));
+                    }
+                    results.entry(analysis).or_default().insert(name, r);
+                }
+                Err(problem) => report.findings.push(finding(problem)),
+            }
+        }
+    }
+
+    // a reduced program may have lost main, leaving only the termination checks
+    let main = match program.functions.get("main") {
+        Some(main) => main,
+        None => return,
+    };
+    let mut rng = Rng::new(seed ^ 0x5eed);
+    for _ in 0..config.runs {
+        let args: Vec<i32> = main.params.iter().map(|_| rng.range(-20, 20)).collect();
+        let mut trace = vec![];
+        if interp::run_traced(program, "main", &args, config.fuel, &mut trace).is_err() {
+            report.incomplete_runs += 1;
+        }
+        report.runs += 1;
+        report.block_exits += trace.len();
+        for (analysis, per_function) in results.iter() {
+            check_trace(&trace, analysis, per_function, seed, &args, &mut report.findings);
+        }
+    }
+}
+
+// the first problem per analysis, function, block and variable is enough
+fn check_trace(trace: &[BlockExit], analysis: &str, per_function: &BTreeMap<&str, ConstResults>, seed: u64,
+               args: &[i32], findings: &mut Vec<Finding>) {
+    for exit in trace.iter() {
+        let results = match per_function.get(exit.function.as_str()) {
+            Some(results) => results,
+            None => continue,
+        };
+        let mut report = |problem: Problem| {
+            let finding = Finding { seed, analysis: analysis.to_string(), function: exit.function.clone(), problem };
+            if !findings.iter().any(|f| same_place(f, &finding)) {
+                findings.push(finding);
+            }
+        };
+        let store = match results.blocks.get(&exit.block) {
+            Some(store) => store,
+            None => {
+                report(Problem::MissedBlock { block: exit.block.clone(), args: args.to_vec() });
+                continue;
+            }
+        };
+        let mut vars: Vec<(&String, &i32)> = exit.ints.iter().collect();
+        vars.sort();
+        for (var, actual) in vars {
+            if let Some(ConstValue::IntConst(claimed)) = store.get(var) {
+                if claimed != actual {
+                    report(Problem::WrongConstant {
+                        block: exit.block.clone(),
+                        var: var.clone(),
+                        claimed: *claimed,
+                        actual: *actual,
+                        args: args.to_vec(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn same_place(a: &Finding, b: &Finding) -> bool {
+    let place = |f: &Finding| match &f.problem {
+        Problem::WrongConstant { block, var, .. } => Some((block.clone(), Some(var.clone()))),
+        Problem::MissedBlock { block, .. } => Some((block.clone(), None)),
+        _ => None,
+    };
+    a.seed == b.seed && a.analysis == b.analysis && a.function == b.function && place(a).is_some() && place(a) == place(b)
+}
+
+// whether checking program with seed still runs into the same kind of problem as finding, with the
+// same analysis and function, which is what the reducer keeps true while it shrinks a program
+pub fn reproduces(program: &Program, finding: &Finding, config: &FuzzConfig) -> bool {
+    let mut report = FuzzReport::default();
+    check_program(program, finding.seed, config, &mut report);
+    report.findings.iter().any(|f| {
+        f.analysis == finding.analysis && f.function == finding.function &&
+            std::mem::discriminant(&f.problem) == std::mem::discriminant(&finding.problem)
+    })
+}
+
+pub fn fuzz(config: &FuzzConfig) -> FuzzReport {
+    let mut report = FuzzReport::default();
+    for i in 0..config.count as u64 {
+        let seed = config.seed.wrapping_add(i);
+        let program = generate(seed, &config.gen);
+        check_program(&program, seed, config, &mut report);
+    }
+    report
+}
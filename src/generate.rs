@@ -0,0 +1,285 @@
+use crate::builder::{function_type, pointer_to, BlockBuilder, FunctionBuilder, ProgramBuilder};
+use crate::lir::{*};
+
+// seeded generator of random well-typed programs, for fuzzing the analyses against the interpreter.
+//
+// every program has a linked "node" struct, int globals, a global pointer to int, two externs, and
+// functions f1..fn plus main. fi only calls fj with j > i, directly or through the function pointer
+// global named after fj, so the call graph is acyclic. blocks jump and branch to arbitrary blocks,
+// but every block bumps a step counter and every backward edge is guarded by it, so each run ends.
+// pointers only ever hold addresses of live objects, so generated programs never fault.
+
+// splitmix64, small and good enough for picking program shapes
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in 0..n, n > 0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // uniform in lo..=hi
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        lo + self.below((hi - lo + 1) as usize) as i32
+    }
+
+    pub fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GenConfig {
+    // functions besides main
+    pub functions: usize,
+    pub max_blocks: usize,
+    pub max_insts: usize,
+    pub max_params: usize,
+    // block executions per activation before backward edges stop being taken
+    pub step_limit: i32,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        GenConfig { functions: 3, max_blocks: 8, max_insts: 6, max_params: 2, step_limit: 20 }
+    }
+}
+
+// what a function may call: name, parameter count, and whether a function pointer global names it
+struct Callee {
+    name: String,
+    params: usize,
+    pointer: Option<Variable>,
+}
+
+// the variables of the function being generated
+struct Vars {
+    ints: Vec<Variable>,
+    // int variables that can be read, including globals
+    readable: Vec<Variable>,
+    int_ptrs: Vec<Variable>,
+    ptr_ptr: Variable,
+    arr: Variable,
+    node: Variable,
+    next: Variable,
+    steps: Variable,
+    cond: Variable,
+}
+
+pub fn generate(seed: u64, config: &GenConfig) -> Program {
+    let mut rng = Rng::new(seed);
+    let mut program = ProgramBuilder::new();
+    let node = Type::Struct("node".to_string());
+    program.structure("node", &[("val", Type::Int), ("next", pointer_to(node.clone()))]);
+    let globals: Vec<Variable> = (0..2).map(|i| program.global(&format!("g{}", i), Type::Int)).collect();
+    let global_ptr = program.global("gp", pointer_to(Type::Int));
+    program.external("input", Some(Type::Int), vec![]);
+    program.external("output", None, vec![Type::Int]);
+
+    // signatures first, so that callers know their callees
+    let mut callees = vec![];
+    for i in 1..=config.functions {
+        let name = format!("f{}", i);
+        let params = rng.below(config.max_params + 1);
+        let pointer = if rng.chance(50) {
+            let typ = pointer_to(function_type(Some(Type::Int), vec![Type::Int; params]));
+            Some(program.global(&name, typ))
+        } else {
+            None
+        };
+        callees.push(Callee { name, params, pointer });
+    }
+
+    for i in (0..=config.functions).rev() {
+        let (name, params) = if i == 0 {
+            ("main".to_string(), rng.below(config.max_params + 1))
+        } else {
+            (callees[i - 1].name.clone(), callees[i - 1].params)
+        };
+        let mut function = FunctionBuilder::new(&name, Some(Type::Int));
+        let mut gen = FunctionGen { rng: &mut rng, config, callees: &callees[i..], global_ptr: &global_ptr };
+        gen.body(&mut function, params, &globals, &node);
+        program.add(function);
+    }
+    program.finish().expect("generated programs are well formed")
+}
+
+struct FunctionGen<'a> {
+    rng: &'a mut Rng,
+    config: &'a GenConfig,
+    // the functions that may be called from this one
+    callees: &'a [Callee],
+    global_ptr: &'a Variable,
+}
+
+impl<'a> FunctionGen<'a> {
+    fn body(&mut self, function: &mut FunctionBuilder, params: usize, globals: &[Variable], node: &Type) {
+        let mut ints: Vec<Variable> = (0..params).map(|i| function.param(&format!("a{}", i), Type::Int)).collect();
+        let locals = 2 + self.rng.below(3);
+        ints.extend((0..locals).map(|i| function.local(&format!("v{}", i), Type::Int)));
+        let mut readable = ints.clone();
+        readable.extend(globals.iter().cloned());
+        let vars = Vars {
+            int_ptrs: (0..2).map(|i| function.local(&format!("p{}", i), pointer_to(Type::Int))).collect(),
+            ptr_ptr: function.local("pp", pointer_to(pointer_to(Type::Int))),
+            arr: function.local("arr", pointer_to(Type::Int)),
+            node: function.local("n", pointer_to(node.clone())),
+            next: function.local("nn", pointer_to(pointer_to(node.clone()))),
+            steps: function.local("steps", Type::Int),
+            cond: function.local("c", Type::Int),
+            ints,
+            readable,
+        };
+
+        // entry points every pointer at something live before any block can use it, and gives every
+        // int local a value, as the analyses take reading an unset variable to be undefined
+        let blocks = 1 + self.rng.below(self.config.max_blocks);
+        let mut entry = function.block("entry")
+            .copy(&vars.steps, 0)
+            .copy(&vars.cond, 0)
+            .addr_of(&vars.int_ptrs[0], &vars.ints[0])
+            .addr_of(&vars.int_ptrs[1], &globals[0])
+            .addr_of(&vars.ptr_ptr, &vars.int_ptrs[0])
+            .alloc(&vars.arr, 4)
+            .alloc(&vars.node, 1)
+            .gfp(&vars.next, &vars.node, "next")
+            .addr_of(self.global_ptr, &globals[1]);
+        for var in vars.ints.iter().skip(params) {
+            entry = entry.copy(var, self.rng.range(-5, 5));
+        }
+        entry.jump("bb0");
+
+        for b in 0..blocks {
+            let mut block = function.block(&format!("bb{}", b))
+                .arith(&vars.steps, ArithOp::Add, &vars.steps, 1);
+            for _ in 0..self.rng.below(self.config.max_insts + 1) {
+                block = self.instruction(block, &vars);
+            }
+            if b + 1 == blocks {
+                let ret = self.rng.pick(&vars.readable).clone();
+                block.ret(Some(&ret));
+                continue;
+            }
+            let exit = format!("bb{}", blocks - 1);
+            self.terminal(block, &vars, b, blocks, &exit);
+        }
+    }
+
+    fn int_operand(&mut self, vars: &Vars) -> Operand {
+        if self.rng.chance(30) {
+            Operand::CInt(self.rng.range(-10, 10))
+        } else {
+            Operand::from(self.rng.pick(&vars.readable))
+        }
+    }
+
+    // an int variable to assign: a local, param or global
+    fn int_target(&mut self, vars: &Vars) -> Variable {
+        self.rng.pick(&vars.readable).clone()
+    }
+
+    fn instruction<'b>(&mut self, block: BlockBuilder<'b>, vars: &Vars) -> BlockBuilder<'b> {
+        let p = self.rng.pick(&vars.int_ptrs).clone();
+        match self.rng.below(14) {
+            0 | 1 => {
+                let lhs = self.int_target(vars);
+                let op = self.int_operand(vars);
+                block.copy(&lhs, op)
+            }
+            2..=4 => {
+                let lhs = self.int_target(vars);
+                let aop = self.rng.pick(&[ArithOp::Add, ArithOp::Subtract, ArithOp::Multiply, ArithOp::Divide]).clone();
+                let op1 = self.int_operand(vars);
+                // only divide by non-zero constants so that runs do not fault
+                let op2 = match aop {
+                    ArithOp::Divide => Operand::CInt(*self.rng.pick(&[-3, -2, -1, 1, 2, 3, 7])),
+                    _ => self.int_operand(vars),
+                };
+                block.arith(&lhs, aop, op1, op2)
+            }
+            5 => {
+                let lhs = self.int_target(vars);
+                let rop = self.rng.pick(&[RelaOp::Eq, RelaOp::Neq, RelaOp::Less, RelaOp::LessEq, RelaOp::Greater, RelaOp::GreaterEq]).clone();
+                let op1 = self.int_operand(vars);
+                let op2 = self.int_operand(vars);
+                block.cmp(&lhs, rop, op1, op2)
+            }
+            6 => {
+                let lhs = self.int_target(vars);
+                let src = if self.rng.chance(25) { self.global_ptr.clone() } else { p };
+                block.load(&lhs, &src)
+            }
+            7 => {
+                let op = self.int_operand(vars);
+                block.store(&p, op)
+            }
+            8 => {
+                let target = self.int_target(vars);
+                block.addr_of(&p, &target)
+            }
+            9 => match self.rng.below(4) {
+                0 => block.gep(&p, &vars.arr, self.rng.range(0, 3)),
+                1 => block.gfp(&p, &vars.node, "val"),
+                2 => block.copy(&p, &vars.int_ptrs[0]),
+                _ => block.load(&p, &vars.ptr_ptr),
+            },
+            10 => block.store(&vars.ptr_ptr, &p),
+            11 => block.store(&vars.next, &vars.node),
+            12 => {
+                let lhs = self.int_target(vars);
+                block.call_ext(Some(&lhs), "input", vec![])
+            }
+            _ => {
+                let op = self.int_operand(vars);
+                block.call_ext(None, "output", vec![op])
+            }
+        }
+    }
+
+    fn terminal(&mut self, block: BlockBuilder, vars: &Vars, b: usize, blocks: usize, exit: &str) {
+        let target = self.rng.below(blocks);
+        let next = format!("bb{}", target);
+        match self.rng.below(10) {
+            // backward edges only while the step budget lasts
+            _ if target <= b => {
+                block.cmp(&vars.cond, RelaOp::Less, &vars.steps, self.config.step_limit)
+                    .branch(&vars.cond, &next, exit);
+            }
+            0..=2 => block.jump(&next),
+            3..=6 => {
+                let cond = self.int_operand(vars);
+                let other = format!("bb{}", b + 1 + self.rng.below(blocks - b - 1));
+                block.branch(cond, &next, &other)
+            }
+            _ if self.callees.is_empty() => block.jump(&next),
+            _ => {
+                let callees = self.callees;
+                let callee = &callees[self.rng.below(callees.len())];
+                let args: Vec<Operand> = (0..callee.params).map(|_| self.int_operand(vars)).collect();
+                let lhs = if self.rng.chance(80) { Some(self.int_target(vars)) } else { None };
+                match &callee.pointer {
+                    Some(pointer) if self.rng.chance(50) => block.call_indirect(lhs.as_ref(), pointer, args, &next),
+                    _ => block.call(lhs.as_ref(), &callee.name, args, &next),
+                }
+            }
+        }
+    }
+}
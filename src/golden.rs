@@ -0,0 +1,115 @@
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+use crate::lir::Program;
+use crate::report::OutputFormat;
+use crate::{congruence, constants, control, intervals, parity, rdef, sccp, sign, stats, zones};
+
+// golden-output regression tests. every <dir>/<name>.lir.json is run in-process through each
+// analysis that has a <dir>/<name>.<analysis>.expected file, and the normalized text output is
+// compared with it. with update set, the expected files of every analysis are (re)written instead.
+
+pub const ANALYSES: [&str; 10] = ["constants", "intervals", "sign", "parity", "zones", "congruence", "sccp", "rdef", "control", "stats"];
+
+// the text output of an analysis over every function of a program, in name order, grouped the same
+// way as lirtool --all-functions
+pub fn render(program: &Program, analysis: &str) -> String {
+    let render: fn(&Program, &str, OutputFormat) -> String = match analysis {
+        "constants" => constants::render,
+        "intervals" => intervals::render,
+        "sign" => sign::render,
+        "parity" => parity::render,
+        "zones" => zones::render,
+        "congruence" => congruence::render,
+        "sccp" => sccp::render,
+        "rdef" => rdef::render,
+        "control" => control::render,
+        "stats" => return stats::render(program, OutputFormat::Text),
+        _ => panic!("unknown analysis {}", analysis),
+    };
+    let mut names: Vec<&String> = program.functions.keys().collect();
+    names.sort();
+    let mut output = String::new();
+    for name in names {
+        if !output.is_empty() && !output.ends_with("\n\n") {
+            output.push('\n');
+        }
+        output.push_str(&format!("function {}:\n", name));
+        output.push_str(&render(program, name, OutputFormat::Text));
+    }
+    output
+}
+
+// line endings and trailing whitespace are not significant, and neither are blank lines at the end
+pub fn normalize(output: &str) -> String {
+    let lines: Vec<&str> = output.lines().map(|line| line.trim_end()).collect();
+    let end = lines.iter().rposition(|line| !line.is_empty()).map_or(0, |i| i + 1);
+    lines[..end].iter().map(|line| format!("{}\n", line)).collect()
+}
+
+// the lines only in expected ("-") and only in actual ("+"), in order, from a longest common subsequence
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = String::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push_str(&format!("{:>4} - {}\n", i + 1, old[i]));
+            i += 1;
+        } else {
+            diff.push_str(&format!("{:>4} + {}\n", j + 1, new[j]));
+            j += 1;
+        }
+    }
+    diff
+}
+
+// run every golden test in dir and return the number passed and the names of those that failed
+pub fn check_dir<P: AsRef<Path>>(dir: P, update: bool) -> Result<(usize, Vec<String>)> {
+    let mut programs: Vec<_> = fs::read_dir(dir.as_ref())?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.to_str().is_some_and(|p| p.ends_with(".lir.json")))
+        .collect();
+    programs.sort();
+
+    let mut passed = 0;
+    let mut failed = vec![];
+    for path in programs {
+        let file_name = path.file_name().and_then(|s| s.to_str()).unwrap();
+        let name = file_name.trim_end_matches(".lir.json");
+        let program = Program::parse_json(&fs::read_to_string(&path)?);
+        for analysis in ANALYSES {
+            let expected_path = dir.as_ref().join(format!("{}.{}.expected", name, analysis));
+            let test = format!("{}.{}", name, analysis);
+            if !update && !expected_path.exists() {
+                continue;
+            }
+            let actual = normalize(&render(&program, analysis));
+            if update {
+                fs::write(&expected_path, &actual)?;
+                println!("{} updated", test);
+                continue;
+            }
+            let expected = normalize(&fs::read_to_string(&expected_path)?);
+            if expected == actual {
+                println!("{} passed", test);
+                passed += 1;
+            } else {
+                println!("{} FAILED\n{}", test, diff(&expected, &actual));
+                failed.push(test);
+            }
+        }
+    }
+    Ok((passed, failed))
+}
@@ -0,0 +1,300 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::cfg::Cfg;
+use crate::lir::{*};
+use crate::liveness::Liveness;
+
+// inlining of direct calls: the body of the callee is copied into the caller with its blocks and
+// locals renamed apart, the arguments are copied into the renamed parameters, and every $ret turns
+// into a copy of the returned value to the lhs of the call and a jump to the block after it.
+// callees larger than the size limit, callees that may (indirectly) call themselves, and callees
+// whose locals cannot be copied faithfully are left alone. each pass inlines the callees as they
+// were before it, so calls brought in by inlining are only considered by the next pass.
+
+// callees with at most this many instructions and terminals are inlined
+pub const DEFAULT_MAX_SIZE: usize = 20;
+
+pub fn inline(program: &Program) -> (Program, usize) {
+    inline_calls(program, DEFAULT_MAX_SIZE)
+}
+
+pub fn inline_calls(program: &Program, max_size: usize) -> (Program, usize) {
+    let mut result = program.clone();
+    let mut inlined = 0;
+    for name in program.functions.keys() {
+        let (function, n) = inline_function(program, name, max_size);
+        result.functions.insert(name.clone(), function);
+        inlined += n;
+    }
+    (result, inlined)
+}
+
+// the function with its calls to small, non-recursive functions inlined, and how many there were
+pub fn inline_function(program: &Program, function_name: &str, max_size: usize) -> (Function, usize) {
+    let mut function = program.functions[function_name].clone();
+    let recursive = recursive(program);
+    let mut sites: Vec<String> = function.body.keys().cloned().collect();
+    sites.sort();
+
+    let mut inlined = 0;
+    for bb_name in sites {
+        let (lhs, callee) = match &function.body[&bb_name].term {
+            Terminal::CallDirect { lhs, callee, .. } => (lhs.clone(), callee),
+            _ => continue,
+        };
+        let callee = match program.functions.get(callee) {
+            Some(callee) if !recursive.contains(&callee.id) && size(callee) <= max_size && !shadows(program, &function, callee) => callee,
+            _ => continue,
+        };
+        if let Some(zeroed) = zeroed_locals(program, callee, &lhs) {
+            splice(program, &mut function, &bb_name, callee, &zeroed);
+            inlined += 1;
+        }
+    }
+    (function, inlined)
+}
+
+// instructions and terminals of the reachable blocks
+pub fn size(function: &Function) -> usize {
+    Cfg::new(function).rpo.iter().map(|bb| function.body[bb].insts.len() + 1).sum()
+}
+
+// the globals some instruction may overwrite, by assigning them or through their address
+fn written_globals(program: &Program) -> HashSet<String> {
+    let mut written = HashSet::new();
+    for bb in program.functions.values().flat_map(|f| f.body.values()) {
+        for inst in bb.insts.iter() {
+            match inst {
+                Instruction::AddrOf { lhs, rhs } => written.extend([lhs.name.clone(), rhs.name.clone()]),
+                Instruction::Alloc { lhs, .. } |
+                Instruction::Copy { lhs, .. } |
+                Instruction::Gep { lhs, .. } |
+                Instruction::Arith { lhs, .. } |
+                Instruction::Load { lhs, .. } |
+                Instruction::Gfp { lhs, .. } |
+                Instruction::Cmp { lhs, .. } |
+                Instruction::CallExt { lhs: Some(lhs), .. } => { written.insert(lhs.name.clone()); }
+                Instruction::Store { .. } |
+                Instruction::CallExt { lhs: None, .. } => {}
+            }
+        }
+        if let Terminal::CallDirect { lhs: Some(lhs), .. } | Terminal::CallIndirect { lhs: Some(lhs), .. } = &bb.term {
+            written.insert(lhs.name.clone());
+        }
+    }
+    written
+}
+
+// the functions a call may reach, by name: direct calls, and indirect calls through a pointer of the
+// right type to any function with a global pointing to it. a call through such a global that nothing
+// writes can only reach the function it is named after
+fn callees(program: &Program, function: &Function, written: &HashSet<String>) -> BTreeSet<String> {
+    let mut callees = BTreeSet::new();
+    for bb in function.body.values() {
+        match &bb.term {
+            Terminal::CallDirect { callee, .. } if program.functions.contains_key(callee) => {
+                callees.insert(callee.clone());
+            }
+            Terminal::CallIndirect { callee, .. } if program.functions.contains_key(&callee.name)
+                && !written.contains(&callee.name) && program.globals.iter().any(|g| g.name == callee.name) => {
+                callees.insert(callee.name.clone());
+            }
+            Terminal::CallIndirect { callee, .. } => {
+                for global in program.globals.iter().filter(|g| g.typ == callee.typ) {
+                    if program.functions.contains_key(&global.name) {
+                        callees.insert(global.name.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    callees
+}
+
+// the functions that lie on a cycle of the call graph, which inlining would unroll without end
+fn recursive(program: &Program) -> HashSet<String> {
+    let written = written_globals(program);
+    let graph: HashMap<&String, BTreeSet<String>> = program.functions.iter()
+        .map(|(name, function)| (name, callees(program, function, &written)))
+        .collect();
+    let mut recursive = HashSet::new();
+    for name in program.functions.keys() {
+        let mut seen: HashSet<&String> = HashSet::new();
+        let mut worklist: Vec<&String> = graph[name].iter().collect();
+        while let Some(current) = worklist.pop() {
+            if current == name {
+                recursive.insert(name.clone());
+                break;
+            }
+            if seen.insert(current) {
+                worklist.extend(graph[current].iter());
+            }
+        }
+    }
+    recursive
+}
+
+// whether the caller declares a local named like a global, which the copied body would then read
+fn shadows(program: &Program, caller: &Function, callee: &Function) -> bool {
+    let own: HashSet<&String> = callee.locals.iter().chain(callee.params.iter()).map(|v| &v.name).collect();
+    caller.locals.iter().chain(caller.params.iter())
+        .any(|v| !own.contains(&v.name) && program.globals.iter().any(|g| g.name == v.name))
+}
+
+// every call starts with its locals zeroed, while the copies of an inlined body keep their values
+// between executions. the int locals the callee may read before writing them are zeroed at every
+// call site; the callee is not inlined when that does not suffice, or when it takes the address of
+// a local, which would then be the same object on every execution
+fn zeroed_locals(program: &Program, callee: &Function, lhs: &Option<Variable>) -> Option<Vec<Variable>> {
+    if !callee.body.contains_key("entry") {
+        return None;
+    }
+    let locals: HashSet<&String> = callee.locals.iter().chain(callee.params.iter()).map(|v| &v.name).collect();
+    let mut returns_nothing = false;
+    for bb in callee.body.values() {
+        for inst in bb.insts.iter() {
+            if matches!(inst, Instruction::AddrOf { rhs, .. } if locals.contains(&rhs.name)) {
+                return None;
+            }
+        }
+        returns_nothing |= matches!(bb.term, Terminal::Ret(None));
+    }
+    // a call that returns nothing leaves zero in its lhs, which only ints can be set to
+    if returns_nothing && lhs.as_ref().is_some_and(|lhs| lhs.typ != Type::Int) {
+        return None;
+    }
+    let liveness = Liveness::new(program, callee);
+    let live = &liveness.live_in["entry"];
+    let read_first: Vec<&Variable> = callee.locals.iter()
+        .filter(|v| live.contains(&v.name))
+        .collect();
+    if read_first.iter().any(|v| v.typ != Type::Int) {
+        return None;
+    }
+    Some(read_first.into_iter().cloned().collect())
+}
+
+// a prefix that renames the blocks and locals of the callee apart from those of the caller
+fn prefix(program: &Program, caller: &Function, callee: &Function) -> String {
+    let taken: HashSet<&String> = caller.body.keys()
+        .chain(caller.locals.iter().chain(caller.params.iter()).chain(program.globals.iter()).map(|v| &v.name))
+        .collect();
+    let names: Vec<&String> = callee.body.keys()
+        .chain(callee.locals.iter().chain(callee.params.iter()).map(|v| &v.name))
+        .collect();
+    let mut n = 1;
+    loop {
+        let prefix = format!("{}_{}_", callee.id, n);
+        if names.iter().all(|name| !taken.contains(&format!("{}{}", prefix, name))) {
+            return prefix;
+        }
+        n += 1;
+    }
+}
+
+struct Renamer<'a> {
+    prefix: String,
+    locals: HashSet<&'a String>,
+    caller: String,
+}
+
+impl Renamer<'_> {
+    fn var(&self, var: &Variable) -> Variable {
+        if !self.locals.contains(&var.name) {
+            return var.clone();
+        }
+        Variable { name: format!("{}{}", self.prefix, var.name), typ: var.typ.clone(), scope: Some(self.caller.clone()) }
+    }
+
+    fn op(&self, op: &Operand) -> Operand {
+        match op {
+            Operand::Var(v) => Operand::Var(self.var(v)),
+            Operand::CInt(_) => op.clone(),
+        }
+    }
+
+    fn block(&self, bb: &str) -> String {
+        format!("{}{}", self.prefix, bb)
+    }
+
+    fn inst(&self, inst: &Instruction) -> Instruction {
+        match inst {
+            Instruction::AddrOf { lhs, rhs } => Instruction::AddrOf { lhs: self.var(lhs), rhs: self.var(rhs) },
+            Instruction::Alloc { lhs, num, id } => {
+                // allocation sites stay distinct from those of the caller and of other copies
+                let id = Variable { name: format!("{}{}", self.prefix, id.name), ..id.clone() };
+                Instruction::Alloc { lhs: self.var(lhs), num: self.op(num), id }
+            }
+            Instruction::Copy { lhs, op } => Instruction::Copy { lhs: self.var(lhs), op: self.op(op) },
+            Instruction::Gep { lhs, src, idx } => Instruction::Gep { lhs: self.var(lhs), src: self.var(src), idx: self.op(idx) },
+            Instruction::Arith { lhs, aop, op1, op2 } =>
+                Instruction::Arith { lhs: self.var(lhs), aop: aop.clone(), op1: self.op(op1), op2: self.op(op2) },
+            Instruction::Load { lhs, src } => Instruction::Load { lhs: self.var(lhs), src: self.var(src) },
+            Instruction::Store { dst, op } => Instruction::Store { dst: self.var(dst), op: self.op(op) },
+            Instruction::Gfp { lhs, src, field } => Instruction::Gfp { lhs: self.var(lhs), src: self.var(src), field: field.clone() },
+            Instruction::Cmp { lhs, rop, op1, op2 } =>
+                Instruction::Cmp { lhs: self.var(lhs), rop: rop.clone(), op1: self.op(op1), op2: self.op(op2) },
+            Instruction::CallExt { lhs, ext_callee, args } => Instruction::CallExt {
+                lhs: lhs.as_ref().map(|lhs| self.var(lhs)),
+                ext_callee: ext_callee.clone(),
+                args: args.iter().map(|a| self.op(a)).collect(),
+            },
+        }
+    }
+
+    // the terminal of a copied block; returns store the result and continue after the call
+    fn term(&self, term: &Terminal, lhs: &Option<Variable>, next_bb: &str, insts: &mut Vec<Instruction>) -> Terminal {
+        match term {
+            Terminal::Jump(target) => Terminal::Jump(self.block(target)),
+            Terminal::Branch { cond, tt, ff } => Terminal::Branch { cond: self.op(cond), tt: self.block(tt), ff: self.block(ff) },
+            Terminal::Ret(op) => {
+                if let Some(lhs) = lhs {
+                    let op = op.as_ref().map(|op| self.op(op)).unwrap_or(Operand::CInt(0));
+                    insts.push(Instruction::Copy { lhs: lhs.clone(), op });
+                }
+                Terminal::Jump(next_bb.to_string())
+            }
+            Terminal::CallDirect { lhs, callee, args, next_bb } => Terminal::CallDirect {
+                lhs: lhs.as_ref().map(|lhs| self.var(lhs)),
+                callee: callee.clone(),
+                args: args.iter().map(|a| self.op(a)).collect(),
+                next_bb: self.block(next_bb),
+            },
+            Terminal::CallIndirect { lhs, callee, args, next_bb } => Terminal::CallIndirect {
+                lhs: lhs.as_ref().map(|lhs| self.var(lhs)),
+                callee: self.var(callee),
+                args: args.iter().map(|a| self.op(a)).collect(),
+                next_bb: self.block(next_bb),
+            },
+        }
+    }
+}
+
+// replaces the call ending block site by a copy of the body of callee
+fn splice(program: &Program, function: &mut Function, site: &str, callee: &Function, zeroed: &[Variable]) {
+    let (lhs, args, next_bb) = match &function.body[site].term {
+        Terminal::CallDirect { lhs, args, next_bb, .. } => (lhs.clone(), args.clone(), next_bb.clone()),
+        _ => unreachable!(),
+    };
+    let renamer = Renamer {
+        prefix: prefix(program, function, callee),
+        locals: callee.locals.iter().chain(callee.params.iter()).map(|v| &v.name).collect(),
+        caller: function.id.clone(),
+    };
+    function.locals.extend(callee.params.iter().chain(callee.locals.iter()).map(|v| renamer.var(v)));
+
+    let bb = function.body.get_mut(site).unwrap();
+    for (param, arg) in callee.params.iter().zip(args.iter()) {
+        bb.insts.push(Instruction::Copy { lhs: renamer.var(param), op: arg.clone() });
+    }
+    bb.insts.extend(zeroed.iter().map(|v| Instruction::Copy { lhs: renamer.var(v), op: Operand::CInt(0) }));
+    bb.term = Terminal::Jump(renamer.block("entry"));
+
+    for bb_name in Cfg::new(callee).rpo.iter() {
+        let bb = &callee.body[bb_name];
+        let mut insts: Vec<Instruction> = bb.insts.iter().map(|inst| renamer.inst(inst)).collect();
+        let term = renamer.term(&bb.term, &lhs, &next_bb, &mut insts);
+        let id = renamer.block(bb_name);
+        function.body.insert(id.clone(), Block { id, insts, term });
+    }
+}
@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::lir::{*};
+
+// a concrete lir interpreter. every variable lives in a memory object of its own so $addrof works
+// uniformly; structs are laid out flat, one cell per int, pointer or function field. arithmetic wraps
+// like i32 on the target, uninitialized memory reads as 0 or null, and extern calls are recorded and
+// return the zero value of their return type.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i32),
+    Null,
+    // object id and cell offset
+    Ptr(usize, usize),
+    Func(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Null => write!(f, "null"),
+            // object ids depend on allocation order, so they are not shown
+            Value::Ptr(..) => write!(f, "<ptr>"),
+            Value::Func(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+// a call to an extern function, in the order the calls were made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtCall {
+    pub callee: String,
+    pub args: Vec<Value>,
+}
+
+impl fmt::Display for ExtCall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|a| a.to_string()).collect();
+        write!(f, "{}({})", self.callee, args.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub ret: Option<Value>,
+    pub ext_calls: Vec<ExtCall>,
+    // instructions and terminals executed
+    pub steps: usize,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for call in self.ext_calls.iter() {
+            writeln!(f, "call {}", call)?;
+        }
+        match &self.ret {
+            Some(value) => writeln!(f, "ret {}", value),
+            None => writeln!(f, "ret"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunError {
+    OutOfFuel,
+    StackOverflow,
+    // location is "function.block.index"
+    Fault { location: String, message: String },
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::OutOfFuel => write!(f, "out of fuel"),
+            RunError::StackOverflow => write!(f, "call stack too deep"),
+            RunError::Fault { location, message } => write!(f, "{}: {}", location, message),
+        }
+    }
+}
+
+pub const DEFAULT_FUEL: usize = 1_000_000;
+const MAX_DEPTH: usize = 500;
+
+// the int variables visible to a function activation when it leaves a block, after the terminal
+// took effect (so a call's result is included), which is where the analyses report their stores
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockExit {
+    pub function: String,
+    pub block: String,
+    pub ints: HashMap<String, i32>,
+}
+
+// the result of an $arith on two ints, wrapping around like i32 on the target, i32::MIN / -1
+// included; None for a division by zero, which faults. the analyses fold constants with it
+pub fn arith(aop: &ArithOp, a: i32, b: i32) -> Option<i32> {
+    match aop {
+        ArithOp::Add => Some(a.wrapping_add(b)),
+        ArithOp::Subtract => Some(a.wrapping_sub(b)),
+        ArithOp::Multiply => Some(a.wrapping_mul(b)),
+        ArithOp::Divide if b == 0 => None,
+        ArithOp::Divide => Some(a.wrapping_div(b)),
+    }
+}
+
+// run function_name with the given int arguments (missing ones are 0 or null) for at most fuel steps
+pub fn run(program: &Program, function_name: &str, args: &[i32], fuel: usize) -> Result<Outcome, RunError> {
+    execute(program, function_name, args, fuel, None)
+}
+
+// run, and record every block exit of every activation in trace
+pub fn run_traced(program: &Program, function_name: &str, args: &[i32], fuel: usize,
+                  trace: &mut Vec<BlockExit>) -> Result<Outcome, RunError> {
+    execute(program, function_name, args, fuel, Some(trace))
+}
+
+// run function_name of both programs on every input, for checking that a transform kept the
+// behaviour: the return value and the extern calls must agree, and a run that faults or recurses too
+// deeply must do so in both. inputs on which before runs out of fuel prove nothing and are skipped.
+pub fn compare(before: &Program, after: &Program, function_name: &str, inputs: &[Vec<i32>], fuel: usize)
+               -> Result<(), String> {
+    for args in inputs.iter() {
+        let same = match (run(before, function_name, args, fuel), run(after, function_name, args, fuel)) {
+            (Err(RunError::OutOfFuel), _) => continue,
+            (Ok(a), Ok(b)) => a.to_string() == b.to_string(),
+            (Err(RunError::Fault { .. }), Err(RunError::Fault { .. })) |
+            (Err(RunError::StackOverflow), Err(RunError::StackOverflow)) => true,
+            _ => false,
+        };
+        if !same {
+            let show = |result: Result<Outcome, RunError>| match result {
+                Ok(outcome) => outcome.to_string().trim_end().replace('\n', "; "),
+                Err(e) => e.to_string(),
+            };
+            return Err(format!("{}{:?} gives {} before, but {} after", function_name, args,
+                               show(run(before, function_name, args, fuel)), show(run(after, function_name, args, fuel))));
+        }
+    }
+    Ok(())
+}
+
+fn execute(program: &Program, function_name: &str, args: &[i32], fuel: usize,
+           trace: Option<&mut Vec<BlockExit>>) -> Result<Outcome, RunError> {
+    let function = match program.functions.get(function_name) {
+        Some(function) => function,
+        None => return Err(RunError::Fault { location: function_name.to_string(), message: "no such function".to_string() }),
+    };
+    let mut machine = Machine { program, heap: vec![], globals: HashMap::new(), ext_calls: vec![], steps: 0, fuel, trace };
+    for global in program.globals.iter() {
+        let obj = machine.alloc(&global.typ, 1);
+        // a function pointer global named after a function points to it
+        if let Type::Pointer(t) = &global.typ {
+            if matches!(**t, Type::Function(_)) &&
+                (program.functions.contains_key(&global.name) || program.externs.contains_key(&global.name)) {
+                machine.heap[obj][0] = Value::Func(global.name.clone());
+            }
+        }
+        machine.globals.insert(global.name.clone(), obj);
+    }
+    let args: Vec<Value> = function.params.iter().enumerate()
+        .map(|(i, p)| match (&p.typ, args.get(i)) {
+            (Type::Int, Some(n)) => Value::Int(*n),
+            (typ, _) => zero(typ),
+        })
+        .collect();
+    let ret = machine.call(function, args, 0)?;
+    Ok(Outcome { ret, ext_calls: machine.ext_calls, steps: machine.steps })
+}
+
+fn zero(typ: &Type) -> Value {
+    match typ {
+        Type::Int => Value::Int(0),
+        _ => Value::Null,
+    }
+}
+
+struct Machine<'a> {
+    program: &'a Program,
+    heap: Vec<Vec<Value>>,
+    globals: HashMap<String, usize>,
+    ext_calls: Vec<ExtCall>,
+    steps: usize,
+    fuel: usize,
+    trace: Option<&'a mut Vec<BlockExit>>,
+}
+
+// variable name -> object holding it
+type Frame = HashMap<String, usize>;
+
+impl<'a> Machine<'a> {
+    fn size_of(&self, typ: &Type) -> usize {
+        match typ {
+            Type::Struct(name) => self.program.structs.get(name)
+                .map(|fields| fields.iter().map(|f| self.size_of(&f.typ)).sum())
+                .unwrap_or(0),
+            _ => 1,
+        }
+    }
+
+    fn zeros(&self, typ: &Type, cells: &mut Vec<Value>) {
+        match typ {
+            Type::Struct(name) => self.program.structs.get(name).into_iter().flatten()
+                .for_each(|f| self.zeros(&f.typ, cells)),
+            _ => cells.push(zero(typ)),
+        }
+    }
+
+    // a new object of num zeroed elements of typ
+    fn alloc(&mut self, typ: &Type, num: usize) -> usize {
+        let mut element = vec![];
+        self.zeros(typ, &mut element);
+        let cells = element.iter().cloned().cycle().take(element.len() * num).collect();
+        self.heap.push(cells);
+        self.heap.len() - 1
+    }
+
+    fn tick(&mut self) -> Result<(), RunError> {
+        self.steps += 1;
+        if self.steps > self.fuel { Err(RunError::OutOfFuel) } else { Ok(()) }
+    }
+
+    fn object(&self, frame: &Frame, var: &str) -> usize {
+        // validated programs only name declared variables
+        *frame.get(var).or_else(|| self.globals.get(var)).unwrap()
+    }
+
+    fn read(&self, frame: &Frame, var: &Variable) -> Value {
+        self.heap[self.object(frame, &var.name)][0].clone()
+    }
+
+    fn write(&mut self, frame: &Frame, var: &Variable, value: Value) {
+        let obj = self.object(frame, &var.name);
+        self.heap[obj][0] = value;
+    }
+
+    fn eval(&self, frame: &Frame, op: &Operand) -> Value {
+        match op {
+            Operand::CInt(n) => Value::Int(*n),
+            Operand::Var(v) => self.read(frame, v),
+        }
+    }
+
+    fn int(&self, frame: &Frame, op: &Operand, at: &str) -> Result<i32, RunError> {
+        match self.eval(frame, op) {
+            Value::Int(n) => Ok(n),
+            value => Err(fault(at, format!("expected an int but found {}", value))),
+        }
+    }
+
+    // the cell a pointer value refers to
+    fn cell(&self, ptr: &Value, at: &str) -> Result<(usize, usize), RunError> {
+        match ptr {
+            Value::Ptr(obj, off) if *off < self.heap[*obj].len() => Ok((*obj, *off)),
+            Value::Ptr(..) => Err(fault(at, "out of bounds memory access".to_string())),
+            Value::Null => Err(fault(at, "null pointer dereference".to_string())),
+            value => Err(fault(at, format!("dereference of non-pointer {}", value))),
+        }
+    }
+
+    fn offset(&self, ptr: Value, by: i64, at: &str) -> Result<Value, RunError> {
+        match ptr {
+            Value::Ptr(obj, off) => match usize::try_from(off as i64 + by) {
+                Ok(off) => Ok(Value::Ptr(obj, off)),
+                Err(_) => Err(fault(at, "pointer arithmetic before the start of an object".to_string())),
+            },
+            Value::Null => Err(fault(at, "pointer arithmetic on null".to_string())),
+            value => Err(fault(at, format!("pointer arithmetic on {}", value))),
+        }
+    }
+
+    fn record(&mut self, function: &Function, frame: &Frame, block: &str) {
+        if self.trace.is_none() {
+            return;
+        }
+        let mut ints = HashMap::new();
+        let vars = self.program.globals.iter().chain(function.params.iter()).chain(function.locals.iter());
+        for var in vars.filter(|v| v.typ == Type::Int) {
+            if let Value::Int(n) = self.read(frame, var) {
+                ints.insert(var.name.clone(), n);
+            }
+        }
+        let exit = BlockExit { function: function.id.clone(), block: block.to_string(), ints };
+        self.trace.as_mut().unwrap().push(exit);
+    }
+
+    fn call(&mut self, function: &'a Function, args: Vec<Value>, depth: usize) -> Result<Option<Value>, RunError> {
+        if depth >= MAX_DEPTH {
+            return Err(RunError::StackOverflow);
+        }
+        let mut frame = Frame::new();
+        for var in function.params.iter().chain(function.locals.iter()) {
+            let obj = self.alloc(&var.typ, 1);
+            frame.insert(var.name.clone(), obj);
+        }
+        for (param, arg) in function.params.iter().zip(args) {
+            self.write(&frame, param, arg);
+        }
+
+        let mut bb = match function.body.get("entry") {
+            Some(bb) => bb,
+            None => return Err(fault(&function.id, "no entry block".to_string())),
+        };
+        loop {
+            for (i, inst) in bb.insts.iter().enumerate() {
+                self.tick()?;
+                self.exec(&frame, inst, &format!("{}.{}.{}", function.id, bb.id, i))?;
+            }
+            self.tick()?;
+            let at = format!("{}.{}.{}", function.id, bb.id, bb.insts.len());
+            let next = match &bb.term {
+                Terminal::Jump(target) => target,
+                Terminal::Branch { cond, tt, ff } => {
+                    if self.int(&frame, cond, &at)? != 0 { tt } else { ff }
+                }
+                Terminal::Ret(op) => {
+                    let ret = op.as_ref().map(|op| self.eval(&frame, op));
+                    self.record(function, &frame, &bb.id);
+                    return Ok(ret);
+                }
+                Terminal::CallDirect { lhs, callee, args, next_bb } => {
+                    self.call_named(&frame, lhs, callee, args, depth, &at)?;
+                    next_bb
+                }
+                Terminal::CallIndirect { lhs, callee, args, next_bb } => {
+                    match self.read(&frame, callee) {
+                        Value::Func(name) => self.call_named(&frame, lhs, &name, args, depth, &at)?,
+                        value => return Err(fault(&at, format!("indirect call through {}", value))),
+                    }
+                    next_bb
+                }
+            };
+            self.record(function, &frame, &bb.id);
+            bb = match function.body.get(next) {
+                Some(bb) => bb,
+                None => return Err(fault(&at, format!("jump to unknown block {}", next))),
+            };
+        }
+    }
+
+    fn call_named(&mut self, frame: &Frame, lhs: &Option<Variable>, callee: &str, args: &[Operand],
+                  depth: usize, at: &str) -> Result<(), RunError> {
+        let args: Vec<Value> = args.iter().map(|a| self.eval(frame, a)).collect();
+        let ret = match self.program.functions.get(callee) {
+            Some(function) => self.call(function, args, depth + 1)?,
+            None if self.program.externs.contains_key(callee) => self.call_ext(callee, args),
+            None => return Err(fault(at, format!("call to unknown function {}", callee))),
+        };
+        if let Some(lhs) = lhs {
+            let value = ret.unwrap_or_else(|| zero(&lhs.typ));
+            self.write(frame, lhs, value);
+        }
+        Ok(())
+    }
+
+    fn call_ext(&mut self, callee: &str, args: Vec<Value>) -> Option<Value> {
+        self.ext_calls.push(ExtCall { callee: callee.to_string(), args });
+        match self.program.externs.get(callee) {
+            Some(Type::Function(ft)) => ft.ret_ty.as_ref().map(zero),
+            _ => None,
+        }
+    }
+
+    fn exec(&mut self, frame: &Frame, inst: &Instruction, at: &str) -> Result<(), RunError> {
+        match inst {
+            Instruction::AddrOf { lhs, rhs } => {
+                let obj = self.object(frame, &rhs.name);
+                self.write(frame, lhs, Value::Ptr(obj, 0));
+            }
+            Instruction::Alloc { lhs, num, .. } => {
+                let num = self.int(frame, num, at)?;
+                if num < 0 {
+                    return Err(fault(at, format!("allocation of {} elements", num)));
+                }
+                let typ = match &lhs.typ {
+                    Type::Pointer(t) => (**t).clone(),
+                    typ => return Err(fault(at, format!("allocation into non-pointer type {}", typ))),
+                };
+                let obj = self.alloc(&typ, num as usize);
+                self.write(frame, lhs, Value::Ptr(obj, 0));
+            }
+            Instruction::Copy { lhs, op } => {
+                let value = self.eval(frame, op);
+                self.write(frame, lhs, value);
+            }
+            Instruction::Gep { lhs, src, idx } => {
+                let size = match &src.typ {
+                    Type::Pointer(t) => self.size_of(t) as i64,
+                    _ => 1,
+                };
+                let idx = self.int(frame, idx, at)? as i64;
+                let value = self.offset(self.read(frame, src), idx * size, at)?;
+                self.write(frame, lhs, value);
+            }
+            Instruction::Arith { lhs, aop, op1, op2 } => {
+                let a = self.int(frame, op1, at)?;
+                let b = self.int(frame, op2, at)?;
+                let value = match arith(aop, a, b) {
+                    Some(value) => value,
+                    None => return Err(fault(at, "division by zero".to_string())),
+                };
+                self.write(frame, lhs, Value::Int(value));
+            }
+            Instruction::Load { lhs, src } => {
+                let (obj, off) = self.cell(&self.read(frame, src), at)?;
+                let value = self.heap[obj][off].clone();
+                self.write(frame, lhs, value);
+            }
+            Instruction::Store { dst, op } => {
+                let (obj, off) = self.cell(&self.read(frame, dst), at)?;
+                self.heap[obj][off] = self.eval(frame, op);
+            }
+            Instruction::Gfp { lhs, src, field } => {
+                let fields = match &src.typ {
+                    Type::Pointer(t) => match &**t {
+                        Type::Struct(name) => self.program.structs.get(name),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let fields = match fields {
+                    Some(fields) => fields,
+                    None => return Err(fault(at, format!("field access through {}", src.typ))),
+                };
+                let mut offset = 0;
+                for f in fields.iter() {
+                    if f.name == field.name {
+                        break;
+                    }
+                    offset += self.size_of(&f.typ);
+                }
+                let value = self.offset(self.read(frame, src), offset as i64, at)?;
+                self.write(frame, lhs, value);
+            }
+            Instruction::Cmp { lhs, rop, op1, op2 } => {
+                let a = self.eval(frame, op1);
+                let b = self.eval(frame, op2);
+                let ordering = match (&a, &b) {
+                    (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+                    (Value::Ptr(o1, a), Value::Ptr(o2, b)) if o1 == o2 => Some(a.cmp(b)),
+                    _ => None,
+                };
+                let result = match (rop, ordering) {
+                    (RelaOp::Eq, _) => a == b,
+                    (RelaOp::Neq, _) => a != b,
+                    (RelaOp::Less, Some(o)) => o.is_lt(),
+                    (RelaOp::LessEq, Some(o)) => o.is_le(),
+                    (RelaOp::Greater, Some(o)) => o.is_gt(),
+                    (RelaOp::GreaterEq, Some(o)) => o.is_ge(),
+                    (_, None) => return Err(fault(at, format!("cannot order {} and {}", a, b))),
+                };
+                self.write(frame, lhs, Value::Int(result as i32));
+            }
+            Instruction::CallExt { lhs, ext_callee, args } => {
+                let args: Vec<Value> = args.iter().map(|a| self.eval(frame, a)).collect();
+                let ret = self.call_ext(ext_callee, args);
+                if let Some(lhs) = lhs {
+                    let value = ret.unwrap_or_else(|| zero(&lhs.typ));
+                    self.write(frame, lhs, value);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn fault(at: &str, message: String) -> RunError {
+    RunError::Fault { location: at.to_string(), message }
+}
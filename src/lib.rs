@@ -0,0 +1,129 @@
+pub mod lir;
+pub mod stats;
+pub mod types;
+pub mod cfg;
+pub mod reaching;
+pub mod defuse;
+pub mod slicing;
+pub mod dependence;
+pub mod dot;
+pub mod report;
+pub mod constants;
+pub mod domain;
+pub mod intervals;
+pub mod sign;
+pub mod parity;
+pub mod signparity;
+pub mod zones;
+pub mod congruence;
+pub mod overflow;
+pub mod sccp;
+pub mod rdef;
+pub mod control;
+pub mod validate;
+pub mod interp;
+pub mod builder;
+pub mod generate;
+pub mod fuzz;
+pub mod reduce;
+pub mod constfold;
+pub mod liveness;
+pub mod dce;
+pub mod available;
+pub mod copyprop;
+pub mod cse;
+pub mod licm;
+pub mod inline;
+pub mod passes;
+pub mod golden;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use crate::lir::{Function, Program};
+use crate::defuse::DefUse;
+
+// the stable entry points of the analyses. each takes a parsed program and the name of one of its
+// functions and reports the facts per reachable block, sorted by block and variable name.
+
+pub use crate::constants::IntConstAbsVal as ConstValue;
+pub use crate::intervals::IntConstAbsVal as IntervalValue;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownFunction(String),
+    NoEntryBlock(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownFunction(name) => write!(f, "no function named {}", name),
+            Error::NoEntryBlock(name) => write!(f, "function {} has no entry block", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// the abstract value of every variable the analysis tracked, for each block it reached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreResults<V> {
+    pub function: String,
+    pub blocks: BTreeMap<String, BTreeMap<String, V>>,
+    // blocks processed by the worklist
+    pub iterations: usize,
+}
+
+pub type ConstResults = StoreResults<ConstValue>;
+pub type IntervalResults = StoreResults<IntervalValue>;
+
+// the function the analyses run on, if it exists and has an entry block
+pub fn function<'a>(program: &'a Program, function_name: &str) -> Result<&'a Function> {
+    let function = program.functions.get(function_name)
+        .ok_or_else(|| Error::UnknownFunction(function_name.to_string()))?;
+    if !function.body.contains_key("entry") {
+        return Err(Error::NoEntryBlock(function_name.to_string()));
+    }
+    Ok(function)
+}
+
+pub fn analyze_constants(program: &Program, function_name: &str) -> Result<ConstResults> {
+    function(program, function_name)?;
+    let (store, iterations) = constants::int_const_analysis(program, function_name);
+    let blocks = store.iter()
+        .map(|(bb, store)| (bb.clone(), store.values().map(|(var, val)| (var.clone(), *val)).collect()))
+        .collect();
+    Ok(StoreResults { function: function_name.to_string(), blocks, iterations })
+}
+
+pub fn analyze_intervals(program: &Program, function_name: &str) -> Result<IntervalResults> {
+    function(program, function_name)?;
+    let (store, iterations) = intervals::int_const_analysis(program, function_name);
+    let blocks = store.iter()
+        .map(|(bb, store)| (bb.clone(), store.values().map(|(var, val)| (var.clone(), *val)).collect()))
+        .collect();
+    Ok(StoreResults { function: function_name.to_string(), blocks, iterations })
+}
+
+// the lattice of analyze_constants, so the results of the two can be compared directly
+pub fn analyze_sccp(program: &Program, function_name: &str) -> Result<ConstResults> {
+    function(program, function_name)?;
+    let (store, iterations) = sccp::sccp_analysis(program, function_name);
+    let blocks = store.iter()
+        .map(|(bb, store)| (bb.clone(), store.iter().map(|(var, val)| (var.clone(), *val)).collect()))
+        .collect();
+    Ok(StoreResults { function: function_name.to_string(), blocks, iterations })
+}
+
+// use-def and def-use chains built from reaching definitions
+pub fn analyze_rdef(program: &Program, function_name: &str) -> Result<DefUse> {
+    function(program, function_name)?;
+    Ok(DefUse::new(program, function_name))
+}
+
+// for every reachable block, the branch blocks it is control dependent on with the deciding outcome
+pub fn analyze_control(program: &Program, function_name: &str) -> Result<BTreeMap<String, BTreeSet<(String, bool)>>> {
+    Ok(control::control_analysis(function(program, function_name)?))
+}
@@ -0,0 +1,169 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::cfg::{self, Cfg};
+use crate::lir::{*};
+use crate::liveness::Liveness;
+use crate::reaching::{Effects, Memory};
+
+// loop-invariant code motion: $arith, $cmp, $gfp, $gep and $load instructions of a natural loop
+// whose operands no instruction of the loop writes (or only ones hoisted already) move into a new
+// preheader block that every edge entering the loop from outside now goes through. the variable
+// assigned must be a local written nowhere else in the loop and not live on entry to the header.
+// a $load also needs a loop without calls or stores that may alias it, and instructions that can
+// fault are only hoisted from blocks that dominate every exit of the loop. inner loops go first,
+// so what leaves an inner loop can keep moving out of the loops around it.
+
+pub fn hoist(program: &Program) -> (Program, usize) {
+    let mut result = program.clone();
+    let mut hoisted = 0;
+    for name in program.functions.keys() {
+        let (function, n) = hoist_function(program, name);
+        result.functions.insert(name.clone(), function);
+        hoisted += n;
+    }
+    (result, hoisted)
+}
+
+// the function with invariant instructions moved into preheaders, and how many moved
+pub fn hoist_function(program: &Program, function_name: &str) -> (Function, usize) {
+    let mut function = program.functions[function_name].clone();
+    let memory = Memory::new(program, &function);
+    let mut headers: Vec<(String, usize)> = Cfg::new(&function).loop_depths().into_iter().collect();
+    headers.sort_by(|(h1, d1), (h2, d2)| d2.cmp(d1).then(h1.cmp(h2)));
+
+    let mut hoisted = 0;
+    for (header, _) in headers {
+        // nothing enters a loop headed by entry from outside, so there is no edge to put a preheader on
+        if header != "entry" {
+            hoisted += hoist_loop(program, &mut function, &memory, &header);
+        }
+    }
+    (function, hoisted)
+}
+
+// the instructions licm considers, with the variable they assign
+fn candidate(inst: &Instruction) -> Option<&Variable> {
+    match inst {
+        Instruction::Arith { lhs, .. } |
+        Instruction::Cmp { lhs, .. } |
+        Instruction::Gfp { lhs, .. } |
+        Instruction::Gep { lhs, .. } |
+        Instruction::Load { lhs, .. } => Some(lhs),
+        _ => None,
+    }
+}
+
+// whether executing inst where the loop did not may fault, e.g. on a null pointer
+fn may_fault(inst: &Instruction) -> bool {
+    match inst {
+        Instruction::Arith { aop: ArithOp::Divide, op2, .. } => !matches!(op2, Operand::CInt(c) if *c != 0),
+        Instruction::Cmp { rop: RelaOp::Eq | RelaOp::Neq, .. } => false,
+        Instruction::Cmp { op1, .. } => matches!(op1, Operand::Var(v) if matches!(v.typ, Type::Pointer(_))),
+        Instruction::Gep { .. } |
+        Instruction::Gfp { .. } |
+        Instruction::Load { .. } => true,
+        _ => false,
+    }
+}
+
+// a store through one pointer type may write what a load through the other reads. a pointer to a
+// struct shares its address with the first field, so it may alias pointers of other types
+fn may_alias(store: &Type, load: &Type) -> bool {
+    let to_struct = |t: &Type| matches!(t, Type::Pointer(t) if matches!(**t, Type::Struct(_)));
+    store == load || to_struct(store) || to_struct(load)
+}
+
+fn hoist_loop(program: &Program, function: &mut Function, memory: &Memory, header: &str) -> usize {
+    let cfg = Cfg::new(function);
+    let body = match cfg.natural_loops().remove(header) {
+        Some(body) => body,
+        None => return 0,
+    };
+    // liveness is computed on the program as given, with this function in its current state
+    let mut current = program.clone();
+    current.functions.insert(function.id.clone(), function.clone());
+    let liveness = Liveness::new(&current, &current.functions[&function.id]);
+    let live_at_header = &liveness.live_in[header];
+
+    // how often each variable may be written in the loop, and what else in it touches memory
+    let mut defs: HashMap<String, usize> = HashMap::new();
+    let mut stores: Vec<&Type> = vec![];
+    let mut calls = false;
+    for bb_name in body.iter() {
+        let bb = &function.body[bb_name];
+        for inst in bb.insts.iter() {
+            let effects = Effects::of_inst(inst, memory);
+            effects.strong_defs.into_iter().chain(effects.weak_defs).for_each(|v| *defs.entry(v).or_default() += 1);
+            match inst {
+                Instruction::Store { dst, .. } => stores.push(&dst.typ),
+                Instruction::CallExt { .. } => calls = true,
+                _ => {}
+            }
+        }
+        let effects = Effects::of_term(&bb.term, memory);
+        effects.strong_defs.into_iter().chain(effects.weak_defs).for_each(|v| *defs.entry(v).or_default() += 1);
+        calls |= matches!(bb.term, Terminal::CallDirect { .. } | Terminal::CallIndirect { .. });
+    }
+    let exiting: Vec<&String> = body.iter().filter(|bb| cfg.succs[*bb].iter().any(|s| !body.contains(s))).collect();
+    let shared: HashSet<String> = memory.call_reachable().into_iter().collect();
+    let locals: HashSet<&String> = function.locals.iter().chain(function.params.iter())
+        .map(|v| &v.name)
+        .filter(|name| !shared.contains(*name))
+        .collect();
+
+    // hoisted instructions in the order they were found, each after the ones it reads
+    let mut chosen: Vec<(String, usize)> = vec![];
+    let mut invariant: HashSet<String> = HashSet::new();
+    let order: Vec<&String> = cfg.rpo.iter().filter(|bb| body.contains(*bb)).collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for bb_name in order.iter() {
+            for (index, inst) in function.body[*bb_name].insts.iter().enumerate() {
+                let lhs = match candidate(inst) {
+                    Some(lhs) if !invariant.contains(&lhs.name) => lhs,
+                    _ => continue,
+                };
+                let operands = Effects::of_inst(inst, memory).uses.iter()
+                    .all(|v| !defs.contains_key(v) || invariant.contains(v));
+                let assigned_once = locals.contains(&lhs.name) && defs.get(&lhs.name) == Some(&1)
+                    && !live_at_header.contains(&lhs.name);
+                let safe = !may_fault(inst) || !exiting.is_empty() && exiting.iter().all(|e| cfg.dominates(bb_name, e));
+                let unaliased = match inst {
+                    Instruction::Load { src, .. } => !calls && stores.iter().all(|t| !may_alias(t, &src.typ)),
+                    _ => true,
+                };
+                if operands && assigned_once && safe && unaliased {
+                    chosen.push(((*bb_name).clone(), index));
+                    invariant.insert(lhs.name.clone());
+                    changed = true;
+                }
+            }
+        }
+    }
+    if chosen.is_empty() {
+        return 0;
+    }
+
+    let insts: Vec<Instruction> = chosen.iter().map(|(bb, index)| function.body[bb].insts[*index].clone()).collect();
+    let moved: BTreeSet<&(String, usize)> = chosen.iter().collect();
+    for bb_name in body.iter() {
+        let bb = function.body.get_mut(bb_name).unwrap();
+        let kept = bb.insts.drain(..).enumerate()
+            .filter(|(index, _)| !moved.contains(&(bb_name.clone(), *index)))
+            .map(|(_, inst)| inst)
+            .collect();
+        bb.insts = kept;
+    }
+
+    let mut preheader = format!("{}_preheader", header);
+    while function.body.contains_key(&preheader) {
+        preheader.push('_');
+    }
+    for (bb_name, bb) in function.body.iter_mut() {
+        if !body.contains(bb_name) {
+            cfg::retarget(&mut bb.term, header, &preheader);
+        }
+    }
+    function.body.insert(preheader.clone(), Block { id: preheader, insts, term: Terminal::Jump(header.to_string()) });
+    chosen.len()
+}
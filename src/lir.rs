@@ -2,10 +2,12 @@ use std::collections::HashMap;
 // use std::fs::File;
 use serde::{Deserialize, Serialize};
 use serde_json as json;
-use crate::stats::Stats;
+use crate::stats::{Metrics, Stats};
+use crate::types::{Base, Origin, TypeCensus};
 use std::cmp::Ordering;
+use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Program {
     pub structs: HashMap<String, Vec<Field>>,
     pub globals: Vec<Variable>,
@@ -15,8 +17,8 @@ pub struct Program {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Hash, Eq, Clone)]
 pub struct Field {
-    name: String,
-    typ: Type,
+    pub name: String,
+    pub typ: Type,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Hash, Eq, Clone)]
@@ -62,7 +64,7 @@ pub struct Variable {
 
 impl PartialOrd for Variable {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.name.cmp(&other.name))
+        Some(self.cmp(other))
     }
 }
 
@@ -72,7 +74,7 @@ impl Ord for Variable {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Function {
     pub id: String,
     pub ret_ty: Option<Type>,
@@ -218,6 +220,204 @@ pub enum Operand {
     CInt(i32),
 }
 
+impl Instruction {
+    // the textual lir opcode, without the leading $
+    pub fn opcode(&self) -> &'static str {
+        match self {
+            Instruction::AddrOf { .. } => "addrof",
+            Instruction::Alloc { .. } => "alloc",
+            Instruction::Copy { .. } => "copy",
+            Instruction::Gep { .. } => "gep",
+            Instruction::Arith { .. } => "arith",
+            Instruction::Load { .. } => "load",
+            Instruction::Store { .. } => "store",
+            Instruction::Gfp { .. } => "gfp",
+            Instruction::Cmp { .. } => "cmp",
+            Instruction::CallExt { .. } => "call_ext",
+        }
+    }
+}
+
+impl Terminal {
+    // the textual lir opcode, without the leading $
+    pub fn opcode(&self) -> &'static str {
+        match self {
+            Terminal::Jump(_) => "jump",
+            Terminal::Branch { .. } => "branch",
+            Terminal::Ret(_) => "ret",
+            Terminal::CallDirect { .. } => "call_dir",
+            Terminal::CallIndirect { .. } => "call_idr",
+        }
+    }
+}
+
+// textual lir syntax, used when rendering instructions for people rather than tools
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Var(v) => write!(f, "{}", v.name),
+            Operand::CInt(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+impl fmt::Display for ArithOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            ArithOp::Add => "add",
+            ArithOp::Subtract => "sub",
+            ArithOp::Multiply => "mul",
+            ArithOp::Divide => "div",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for RelaOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self {
+            RelaOp::Neq => "neq",
+            RelaOp::Eq => "eq",
+            RelaOp::Less => "lt",
+            RelaOp::LessEq => "lte",
+            RelaOp::Greater => "gt",
+            RelaOp::GreaterEq => "gte",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Struct(name) => write!(f, "{}", name),
+            Type::Pointer(t) => write!(f, "&{}", t),
+            Type::Function(ft) => {
+                let params: Vec<String> = ft.param_ty.iter().map(|t| t.to_string()).collect();
+                write!(f, "({}) -> ", params.join(", "))?;
+                match &ft.ret_ty {
+                    Some(t) => write!(f, "{}", t),
+                    None => write!(f, "_"),
+                }
+            }
+        }
+    }
+}
+
+fn join_operands(args: &[Operand]) -> String {
+    args.iter().map(|a| a.to_string()).collect::<Vec<String>>().join(", ")
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::AddrOf { lhs, rhs } => write!(f, "{} = $addrof {}", lhs.name, rhs.name),
+            Instruction::Alloc { lhs, num, id } => write!(f, "{} = $alloc {} [{}]", lhs.name, num, id.name),
+            Instruction::Copy { lhs, op } => write!(f, "{} = $copy {}", lhs.name, op),
+            Instruction::Gep { lhs, src, idx } => write!(f, "{} = $gep {} {}", lhs.name, src.name, idx),
+            Instruction::Arith { lhs, aop, op1, op2 } => write!(f, "{} = $arith {} {} {}", lhs.name, aop, op1, op2),
+            Instruction::Load { lhs, src } => write!(f, "{} = $load {}", lhs.name, src.name),
+            Instruction::Store { dst, op } => write!(f, "$store {} {}", dst.name, op),
+            Instruction::Gfp { lhs, src, field } => write!(f, "{} = $gfp {} {}", lhs.name, src.name, field.name),
+            Instruction::Cmp { lhs, rop, op1, op2 } => write!(f, "{} = $cmp {} {} {}", lhs.name, rop, op1, op2),
+            Instruction::CallExt { lhs: Some(lhs), ext_callee, args } =>
+                write!(f, "{} = $call_ext {}({})", lhs.name, ext_callee, join_operands(args)),
+            Instruction::CallExt { lhs: None, ext_callee, args } =>
+                write!(f, "$call_ext {}({})", ext_callee, join_operands(args)),
+        }
+    }
+}
+
+impl fmt::Display for Terminal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terminal::Jump(target) => write!(f, "$jump {}", target),
+            Terminal::Branch { cond, tt, ff } => write!(f, "$branch {} {} {}", cond, tt, ff),
+            Terminal::Ret(Some(op)) => write!(f, "$ret {}", op),
+            Terminal::Ret(None) => write!(f, "$ret"),
+            Terminal::CallDirect { lhs, callee, args, next_bb } => {
+                if let Some(lhs) = lhs { write!(f, "{} = ", lhs.name)?; }
+                write!(f, "$call_dir {}({}) then {}", callee, join_operands(args), next_bb)
+            }
+            Terminal::CallIndirect { lhs, callee, args, next_bb } => {
+                if let Some(lhs) = lhs { write!(f, "{} = ", lhs.name)?; }
+                write!(f, "$call_idr {}({}) then {}", callee.name, join_operands(args), next_bb)
+            }
+        }
+    }
+}
+
+fn typed_vars(vars: &[Variable]) -> String {
+    vars.iter().map(|v| format!("{}:{}", v.name, v.typ)).collect::<Vec<String>>().join(", ")
+}
+
+// the whole program in textual lir, with structs, globals, externs, functions and blocks sorted
+// by name so that the output is stable
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut structs: Vec<&String> = self.structs.keys().collect();
+        structs.sort();
+        for name in structs {
+            writeln!(f, "struct {} {{", name)?;
+            for field in self.structs[name].iter() {
+                writeln!(f, "  {}:{}", field.name, field.typ)?;
+            }
+            writeln!(f, "}}\n")?;
+        }
+        let mut globals: Vec<&Variable> = self.globals.iter().collect();
+        globals.sort();
+        for global in globals.iter() {
+            writeln!(f, "{}:{}", global.name, global.typ)?;
+        }
+        if !globals.is_empty() { writeln!(f)?; }
+        let mut externs: Vec<&String> = self.externs.keys().collect();
+        externs.sort();
+        for name in externs.iter() {
+            writeln!(f, "extern {}:{}", name, self.externs[*name])?;
+        }
+        if !externs.is_empty() { writeln!(f)?; }
+        let mut functions: Vec<&String> = self.functions.keys().collect();
+        functions.sort();
+        for name in functions {
+            write!(f, "{}", self.functions[name])?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fn {}({}) -> ", self.id, typed_vars(&self.params))?;
+        match &self.ret_ty {
+            Some(t) => writeln!(f, "{} {{", t)?,
+            None => writeln!(f, "_ {{")?,
+        }
+        let mut locals = self.locals.clone();
+        locals.sort();
+        if !locals.is_empty() {
+            writeln!(f, "let {}", typed_vars(&locals))?;
+        }
+        let mut blocks: Vec<&String> = self.body.keys().collect();
+        blocks.sort();
+        for name in blocks {
+            let bb = &self.body[name];
+            writeln!(f, "{}:", name)?;
+            for inst in bb.insts.iter() {
+                writeln!(f, "  {}", inst)?;
+            }
+            writeln!(f, "  {}", bb.term)?;
+        }
+        writeln!(f, "}}\n")
+    }
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Program::new()
+    }
+}
+
 impl Program {
     pub fn new() -> Program {
         Program {
@@ -236,6 +436,10 @@ impl Program {
         json::to_string(&self).unwrap()
     }
 
+    pub fn get_metrics(&self) -> Metrics {
+        Metrics::new(self)
+    }
+
     pub fn get_stats(&self) -> Stats {
         let mut stats = Stats::new();
 
@@ -269,84 +473,15 @@ impl Program {
             .count() as u32;
 
 
-        stats.int_type_num = //self.get_local_num_by_type(Type::Int);
-            self.functions.values()
-                .flat_map(|f| &f.locals)
-                .filter(|v| matches!(&v.typ, Type::Int))
-                .count() as u32;
-
-        stats.int_type_num += self.globals.iter()
-            .filter(|v| matches!(v.typ, Type::Int))
-            .count() as u32;
-
-        stats.struct_type_num = self.functions.values()
-                .flat_map(|f| &f.locals)
-                .filter(|v| matches!(&v.typ, Type::Struct(_)))
-                .count() as u32;
-
-        stats.struct_type_num += self.globals.iter()
-            .filter(|v| matches!(v.typ, Type::Struct(_)))
-            .count() as u32;
-
-        stats.pointer_to_int_num = self.functions.values()
-            .flat_map(|f| &f.locals)
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Int),
-                _ => false,
-            })
-            .count() as u32;
-
-        stats.pointer_to_int_num += self.globals.iter()
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Int),
-                _ => false,
-            })
-            .count() as u32;
-
-        stats.pointer_to_struct_num = self.functions.values()
-            .flat_map(|f| &f.locals)
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Struct(_)),
-                _ => false,
-            })
-            .count() as u32;
-
-        stats.pointer_to_struct_num += self.globals.iter()
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Struct(_)),
-                _ => false,
-            })
-            .count() as u32;
-
-        stats.pointer_to_function_num = self.functions.values()
-            .flat_map(|f| &f.locals)
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Function(_)),
-                _ => false,
-            })
-            .count() as u32;
-
-        stats.pointer_to_function_num += self.globals.iter()
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Function(_)),
-                _ => false,
-            })
-            .count() as u32;
-
-        stats.pointer_to_pointer_num = self.functions.values()
-            .flat_map(|f| &f.locals)
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Pointer(_)),
-                _ => false,
-            })
-            .count() as u32;
-
-        stats.pointer_to_pointer_num += self.globals.iter()
-            .filter(|v| match &v.typ {
-                Type::Pointer(t) => matches!(**t, Type::Pointer(_)),
-                _ => false,
-            })
-            .count() as u32;
+        // the legacy counts cover locals and globals only, and put every pointer to a pointer in one bucket
+        let census = TypeCensus::new(self);
+        let legacy = [Origin::Local, Origin::Global];
+        stats.int_type_num = census.count(&legacy, |s| s.depth == 0 && s.base == Base::Int);
+        stats.struct_type_num = census.count(&legacy, |s| s.depth == 0 && matches!(s.base, Base::Struct(_)));
+        stats.pointer_to_int_num = census.count(&legacy, |s| s.depth == 1 && s.base == Base::Int);
+        stats.pointer_to_struct_num = census.count(&legacy, |s| s.depth == 1 && matches!(s.base, Base::Struct(_)));
+        stats.pointer_to_function_num = census.count(&legacy, |s| s.depth == 1 && s.base == Base::Function);
+        stats.pointer_to_pointer_num = census.count(&legacy, |s| s.depth > 1);
 
         stats
     }
@@ -0,0 +1,597 @@
+use std::fs;
+use std::panic;
+use std::process::exit;
+use std::time::Instant;
+use cs260_proj::{congruence, constants, constfold, control, copyprop, cse, dce, fuzz, generate, inline, interp, intervals, licm, overflow, parity, rdef, reduce, sccp, sign, signparity, stats, validate, zones};
+use cs260_proj::lir::{*};
+use cs260_proj::report::{parse_format, OutputFormat};
+use cs260_proj::reaching::{ProgramPoint, Site};
+use cs260_proj::slicing::{Direction, Slicer};
+use cs260_proj::dependence::Pdg;
+use cs260_proj::copyprop::Scope;
+use cs260_proj::passes::{self, PassManager};
+
+const USAGE: &str = "\
+Usage: lirtool <command> [options] <file> [function] [args...]
+       lirtool generate|fuzz [options]
+
+Commands:
+  constants   integer constant propagation; warns on stderr of arithmetic that may overflow
+  intervals   integer interval analysis; warns of overflows like constants
+  sign        sign (negative, zero, positive) analysis
+  parity      parity (even, odd) analysis
+  signparity  sign and parity analyses together, as a reduced product with --reduced
+  zones       relational bounds x - y <= c between ints (difference-bound matrices)
+  congruence  intervals with congruences modulo powers of two, e.g. the stride of an index
+  sccp        sparse conditional constant propagation
+  rdef        reaching definitions
+  control     control dependences
+  stats       program statistics and code metrics (also --stats)
+  validate    check that the program is well formed and well typed
+  print       print the program (or one function) as textual lir
+  run         interpret a function (default main) with int arguments
+  slice       <file> <function> <block.index> <variable> [--forward] [--lir]
+  pdg         program dependence graph
+  generate    print a random well-typed program
+  fuzz        check constants, intervals and sccp against the interpreter on random programs
+  constfold   fold constants (in every function, or the one given); prints the json
+  dce         remove dead code, unreachable blocks, unused locals and jump chains
+              (in every function, or the one given); prints the json, reports on stderr
+  copyprop    propagate copies, across blocks unless --local; prints the json
+  cse         replace recomputed expressions by copies; prints the json
+  licm        hoist loop-invariant computations into loop preheaders; prints the json
+  inline      inline direct calls of small non-recursive functions (up to --max-size
+              instructions and terminals); prints the json
+  opt         run the transforms given with --passes over every function, validating
+              after each; prints the json, and per pass timing and sizes with -v
+  reduce      shrink a program while its first fuzz finding (with --seed) persists; prints the json
+
+Options:
+  --input-format json   format of <file>; only the json encoding of lir is supported
+  --all-functions       analyze every function of the program, in name order,
+                        grouping the results per function (--all for short)
+  --format text|json|dot
+  --dot                 same as --format dot
+  --fuel <n>            steps the interpreter may take before giving up
+  --seed <n>            seed of the (first) generated program; reduce runs main with the
+                        arguments fuzz picked for that seed, and the transforms (constfold,
+                        dce, copyprop, cse, licm, inline, opt) check that main behaves the same on
+                        inputs drawn from it
+  --count <n>           number of programs to fuzz
+  --passes <list>       comma separated transforms for opt: constfold, dce, copyprop, cse, licm,
+                        inline
+  --max-size <n>        largest callee inline copies, 20 by default
+  --fixpoint            repeat the opt pipeline until it changes nothing
+  -v, --verbose         report timing on stderr
+  -q, --quiet           print only results";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+struct Options {
+    command: String,
+    input_format: String,
+    format: OutputFormat,
+    all: bool,
+    fuel: usize,
+    seed: u64,
+    count: usize,
+    verbosity: Verbosity,
+    // the pipeline of opt
+    passes: Vec<String>,
+    // the largest callee inline copies
+    max_size: usize,
+    // command specific switches: --forward and --lir for slice, --local for copyprop, --fixpoint for opt,
+    // --reduced for signparity
+    switches: Vec<String>,
+    // the file, then the function and any command specific arguments
+    args: Vec<String>,
+}
+
+const COMMANDS: [&str; 26] = [
+    "constants", "intervals", "sign", "parity", "signparity", "zones", "congruence", "sccp", "rdef", "control", "stats", "validate", "print", "run", "slice", "pdg",
+    "generate", "fuzz", "reduce", "constfold", "dce", "copyprop", "cse", "licm", "inline", "opt",
+];
+
+// commands that make up their own programs instead of reading one
+fn generates(command: &str) -> bool {
+    matches!(command, "generate" | "fuzz")
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let command = match args.first() {
+        Some(command) if COMMANDS.contains(&command.as_str()) => command.clone(),
+        Some(command) if command == "--stats" => "stats".to_string(),
+        Some(command) => return Err(format!("unknown command {}", command)),
+        None => return Err("missing command".to_string()),
+    };
+    let mut options = Options {
+        command,
+        input_format: "json".to_string(),
+        format: OutputFormat::Text,
+        all: false,
+        fuel: interp::DEFAULT_FUEL,
+        seed: 0,
+        passes: vec![],
+        max_size: inline::DEFAULT_MAX_SIZE,
+        count: fuzz::FuzzConfig::default().count,
+        verbosity: Verbosity::Normal,
+        switches: vec![],
+        args: vec![],
+    };
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--input-format" => options.input_format = rest.next().ok_or("--input-format needs a value")?.clone(),
+            "--format" => {
+                let name = rest.next().ok_or("--format needs a value")?;
+                options.format = parse_format(name).ok_or(format!("unknown output format {}", name))?;
+            }
+            "--dot" => options.format = OutputFormat::Dot,
+            "--all-functions" | "--all" => options.all = true,
+            "--fuel" => {
+                let fuel = rest.next().ok_or("--fuel needs a value")?;
+                options.fuel = fuel.parse().map_err(|_| format!("invalid fuel {}", fuel))?;
+            }
+            "--seed" => {
+                let seed = rest.next().ok_or("--seed needs a value")?;
+                options.seed = seed.parse().map_err(|_| format!("invalid seed {}", seed))?;
+            }
+            "--count" => {
+                let count = rest.next().ok_or("--count needs a value")?;
+                options.count = count.parse().map_err(|_| format!("invalid count {}", count))?;
+            }
+            "-v" | "--verbose" => options.verbosity = Verbosity::Verbose,
+            "-q" | "--quiet" => options.verbosity = Verbosity::Quiet,
+            "--forward" | "--lir" if options.command == "slice" => options.switches.push(arg.clone()),
+            "--local" if options.command == "copyprop" => options.switches.push(arg.clone()),
+            "--reduced" if options.command == "signparity" => options.switches.push(arg.clone()),
+            "--fixpoint" if options.command == "opt" => options.switches.push(arg.clone()),
+            "--max-size" if options.command == "inline" => {
+                let size = rest.next().ok_or("--max-size needs a value")?;
+                options.max_size = size.parse().map_err(|_| format!("invalid size {}", size))?;
+            }
+            "--passes" if options.command == "opt" => {
+                options.passes = passes::parse_pipeline(rest.next().ok_or("--passes needs a value")?)?;
+            }
+            // negative numbers are arguments of run, not flags
+            flag if flag.starts_with('-') && flag.parse::<i32>().is_err() => return Err(format!("unknown option {}", flag)),
+            _ => options.args.push(arg.clone()),
+        }
+    }
+    if generates(&options.command) {
+        if let Some(arg) = options.args.first() {
+            return Err(format!("{} takes no input file, but got {}", options.command, arg));
+        }
+    } else if options.args.is_empty() {
+        return Err("missing input file".to_string());
+    }
+    if options.command == "opt" && options.passes.is_empty() {
+        return Err("opt needs --passes".to_string());
+    }
+    if options.all && matches!(options.command.as_str(), "run" | "slice" | "generate" | "fuzz" | "reduce") {
+        return Err(format!("{} works on a single function", options.command));
+    }
+    if options.all && matches!(options.command.as_str(), "stats" | "validate" | "print") {
+        return Err(format!("{} works on the whole program", options.command));
+    }
+    let formats: &[OutputFormat] = match options.command.as_str() {
+        "stats" | "validate" | "print" => &[OutputFormat::Text, OutputFormat::Json],
+        "run" | "slice" | "fuzz" | "reduce" | "constfold" | "dce" | "copyprop" | "cse" | "licm" | "inline" | "opt" => &[OutputFormat::Text],
+        "generate" => &[OutputFormat::Text, OutputFormat::Json],
+        _ => &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Dot],
+    };
+    if !formats.contains(&options.format) {
+        return Err(format!("{} does not support {:?} output", options.command, options.format));
+    }
+    Ok(options)
+}
+
+fn load(options: &Options) -> Result<Program, String> {
+    let path = &options.args[0];
+    if options.input_format != "json" || path.ends_with(".lir") {
+        return Err(format!("{}: only json encoded lir is supported, pass the .lir.json file", path));
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{}: not a json lir program: {}", path, e))
+}
+
+// the functions a command runs on: every function with --all-functions, otherwise the one given after the file
+fn selected_functions(program: &Program, options: &Options) -> Result<Vec<String>, String> {
+    if options.all {
+        let mut names: Vec<String> = program.functions.keys().cloned().collect();
+        names.sort();
+        return Ok(names);
+    }
+    match options.args.get(1) {
+        Some(name) => cs260_proj::function(program, name).map(|_| vec![name.clone()]).map_err(|e| e.to_string()),
+        None => Err(format!("{} needs a function name or --all-functions", options.command)),
+    }
+}
+
+fn analyze(program: &Program, options: &Options) -> Result<(), String> {
+    let render: fn(&Program, &str, OutputFormat) -> String = match options.command.as_str() {
+        "constants" => constants::render,
+        "intervals" => intervals::render,
+        "sign" => sign::render,
+        "parity" => parity::render,
+        "signparity" if options.switches.iter().any(|s| s == "--reduced") => signparity::render_reduced,
+        "signparity" => signparity::render,
+        "zones" => zones::render,
+        "congruence" => congruence::render,
+        "sccp" => sccp::render,
+        "rdef" => rdef::render,
+        "control" => control::render,
+        "pdg" => |program, function_name, format| {
+            let pdg = Pdg::new(program, function_name);
+            match format {
+                OutputFormat::Json => format!("{}\n", pdg.as_json()),
+                _ => pdg.as_dot(),
+            }
+        },
+        _ => unreachable!(),
+    };
+    // the file, and the function unless every function is analyzed
+    let expected = if options.all { 1 } else { 2 };
+    if let Some(arg) = options.args.get(expected) {
+        return Err(format!("unexpected argument {}", arg));
+    }
+    if !options.all {
+        let function_name = &selected_functions(program, options)?[0];
+        let start = Instant::now();
+        print!("{}", render(program, function_name, options.format));
+        warn_overflows(program, function_name, options);
+        if options.verbosity == Verbosity::Verbose {
+            eprintln!("{} {}: {:?}", options.command, function_name, start.elapsed());
+        }
+        return Ok(());
+    }
+
+    // every function is analyzed from scratch, and one that makes the analysis fail does not
+    // keep the others from being reported
+    let mut failed = vec![];
+    let mut reports = vec![];
+    let mut last = String::new();
+    for (i, function_name) in selected_functions(program, options)?.iter().enumerate() {
+        if let Err(e) = cs260_proj::function(program, function_name) {
+            eprintln!("{}", e);
+            failed.push(function_name.clone());
+            continue;
+        }
+        let start = Instant::now();
+        let output = match panic::catch_unwind(|| render(program, function_name, options.format)) {
+            Ok(output) => output,
+            Err(_) => {
+                eprintln!("{} failed on function {}", options.command, function_name);
+                failed.push(function_name.clone());
+                continue;
+            }
+        };
+        match options.format {
+            OutputFormat::Text => {
+                // functions are separated by a blank line, unless the output already ends with one
+                if i > 0 && !last.ends_with("\n\n") {
+                    println!();
+                }
+                println!("function {}:", function_name);
+                print!("{}", output);
+            }
+            // the reports are collected into one array, each naming its function
+            OutputFormat::Json => reports.push(output.trim_end().lines().map(|l| format!("  {}", l)).collect::<Vec<_>>().join("\n")),
+            // one digraph per function; graphviz renders each of them
+            OutputFormat::Dot => print!("{}", output),
+        }
+        warn_overflows(program, function_name, options);
+        if options.verbosity == Verbosity::Verbose {
+            eprintln!("{} {}: {:?}", options.command, function_name, start.elapsed());
+        }
+        last = output;
+    }
+    if options.format == OutputFormat::Json {
+        println!("[\n{}\n]", reports.join(",\n"));
+    }
+    if failed.is_empty() { Ok(()) } else { Err(format!("{} failed on {}", options.command, failed.join(", "))) }
+}
+
+// the constant analyses fold ints the way they wrap around; where that may happen, say so on stderr
+fn warn_overflows(program: &Program, function_name: &str, options: &Options) {
+    if matches!(options.command.as_str(), "constants" | "intervals") && options.verbosity > Verbosity::Quiet {
+        overflow::overflow_warnings(program, function_name).iter().for_each(|w| eprintln!("{}", w));
+    }
+}
+
+fn validate(program: &Program, options: &Options) -> Result<(), String> {
+    let errors = match validate::validate(program) {
+        Ok(()) => vec![],
+        Err(errors) => errors,
+    };
+    match options.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&errors).unwrap()),
+        _ => {
+            errors.iter().for_each(|e| println!("{}", e));
+            if errors.is_empty() && options.verbosity > Verbosity::Quiet {
+                println!("ok");
+            }
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(format!("{} error(s) found", errors.len())) }
+}
+
+fn print(program: &Program, options: &Options) -> Result<(), String> {
+    match options.args.get(1) {
+        Some(function_name) => {
+            let function = program.functions.get(function_name)
+                .ok_or(format!("no function named {}", function_name))?;
+            match options.format {
+                OutputFormat::Json => println!("{}", serde_json::to_string(function).unwrap()),
+                _ => print!("{}", function),
+            }
+        }
+        None => match options.format {
+            OutputFormat::Json => println!("{}", program.as_json()),
+            _ => print!("{}", program),
+        },
+    }
+    Ok(())
+}
+
+fn run(program: &Program, options: &Options) -> Result<(), String> {
+    let function_name = options.args.get(1).map(|s| s.as_str()).unwrap_or("main");
+    let args = options.args.iter().skip(2)
+        .map(|a| a.parse::<i32>().map_err(|_| format!("invalid int argument {}", a)))
+        .collect::<Result<Vec<i32>, String>>()?;
+    let function = program.functions.get(function_name).ok_or(format!("no function named {}", function_name))?;
+    if args.len() > function.params.len() {
+        return Err(format!("{} takes {} arguments but {} are given", function_name, function.params.len(), args.len()));
+    }
+    if let Err(errors) = validate::validate(program) {
+        errors.iter().for_each(|e| eprintln!("{}", e));
+        return Err("refusing to run an invalid program".to_string());
+    }
+    let start = Instant::now();
+    let outcome = interp::run(program, function_name, &args, options.fuel).map_err(|e| format!("runtime error: {}", e))?;
+    print!("{}", outcome);
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("{} steps in {:?}", outcome.steps, start.elapsed());
+    }
+    Ok(())
+}
+
+fn slice(program: &Program, options: &Options) -> Result<(), String> {
+    let (function_name, criterion, var) = match &options.args[..] {
+        [_, function_name, criterion, var] => (function_name, criterion, var),
+        _ => return Err("slice needs <file> <function> <block.index> <variable>".to_string()),
+    };
+    let function = cs260_proj::function(program, function_name).map_err(|e| e.to_string())?;
+    let criterion = match ProgramPoint::parse(function_name, criterion) {
+        Some(point) if point.site(function).is_some() => point,
+        _ => return Err(format!("{} is not a program point of {}", criterion, function_name)),
+    };
+    let direction = if options.switches.iter().any(|s| s == "--forward") { Direction::Forward } else { Direction::Backward };
+
+    let slicer = Slicer::new(program, function_name);
+    let slice = slicer.slice(&criterion, var, direction);
+    if options.switches.iter().any(|s| s == "--lir") {
+        println!("{}", slicer.sliced_program(program, &slice).as_json());
+        return Ok(());
+    }
+    for point in slice.iter() {
+        let site = match point.site(function).unwrap() {
+            Site::Inst(inst) => serde_json::to_string(inst).unwrap(),
+            Site::Term(term) => serde_json::to_string(term).unwrap(),
+        };
+        println!("{} {}", point, site);
+    }
+    Ok(())
+}
+
+fn generate(options: &Options) -> Result<(), String> {
+    let program = generate::generate(options.seed, &generate::GenConfig::default());
+    match options.format {
+        OutputFormat::Json => println!("{}", program.as_json()),
+        _ => print!("{}", program),
+    }
+    Ok(())
+}
+
+fn fuzz(options: &Options) -> Result<(), String> {
+    let config = fuzz::FuzzConfig { seed: options.seed, count: options.count, ..Default::default() };
+    let config = if options.fuel == interp::DEFAULT_FUEL { config } else { fuzz::FuzzConfig { fuel: options.fuel, ..config } };
+    let start = Instant::now();
+    let report = fuzz::fuzz(&config);
+    print!("{}", report);
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("fuzzed in {:?}", start.elapsed());
+    }
+    if report.findings.is_empty() { Ok(()) } else { Err(format!("{} finding(s)", report.findings.len())) }
+}
+
+fn reduce(program: &Program, options: &Options) -> Result<(), String> {
+    if let Some(arg) = options.args.get(1) {
+        return Err(format!("unexpected argument {}", arg));
+    }
+    if let Err(errors) = validate::validate(program) {
+        errors.iter().for_each(|e| eprintln!("{}", e));
+        return Err("refusing to reduce an invalid program".to_string());
+    }
+    let config = fuzz::FuzzConfig { seed: options.seed, ..Default::default() };
+    let config = if options.fuel == interp::DEFAULT_FUEL { config } else { fuzz::FuzzConfig { fuel: options.fuel, ..config } };
+    let mut report = fuzz::FuzzReport::default();
+    fuzz::check_program(program, options.seed, &config, &mut report);
+    let finding = report.findings.first().ok_or("the program has no fuzz findings to preserve")?;
+    if options.verbosity > Verbosity::Quiet {
+        eprintln!("reducing while this persists: {}", finding);
+    }
+    let start = Instant::now();
+    let reduction = reduce::reduce(program, |candidate| fuzz::reproduces(candidate, finding, &config));
+    println!("{}", reduction.program.as_json());
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("size {} -> {} in {} attempts ({} kept), {:?}", reduce::size(program), reduce::size(&reduction.program),
+                  reduction.attempts, reduction.accepted, start.elapsed());
+    }
+    Ok(())
+}
+
+// run a transform over every function, or only over the one given after the file, and check on a
+// few random inputs that main still behaves the same
+fn transform<R>(program: &Program, options: &Options, whole: impl Fn(&Program) -> (Program, R),
+                one: impl Fn(&Program, &str) -> (Function, R)) -> Result<(Program, R), String> {
+    if let Some(arg) = options.args.get(2) {
+        return Err(format!("unexpected argument {}", arg));
+    }
+    let (transformed, result) = match options.args.get(1) {
+        None => whole(program),
+        Some(function_name) => {
+            cs260_proj::function(program, function_name).map_err(|e| e.to_string())?;
+            let (function, result) = one(program, function_name);
+            let mut transformed = program.clone();
+            transformed.functions.insert(function_name.clone(), function);
+            (transformed, result)
+        }
+    };
+    check_behaviour(program, &transformed, options)?;
+    Ok((transformed, result))
+}
+
+// main of transformed must behave as in program on a few random inputs
+fn check_behaviour(program: &Program, transformed: &Program, options: &Options) -> Result<(), String> {
+    if let Some(main) = program.functions.get("main") {
+        let mut rng = generate::Rng::new(options.seed);
+        let inputs: Vec<Vec<i32>> = (0..8).map(|_| main.params.iter().map(|_| rng.range(-100, 100)).collect()).collect();
+        interp::compare(program, transformed, "main", &inputs, options.fuel)
+            .map_err(|e| format!("{} changed the behaviour of the program: {}", options.command, e))?;
+    }
+    Ok(())
+}
+
+fn constfold(program: &Program, options: &Options) -> Result<(), String> {
+    let (folded, stats) = transform(program, options, constfold::fold, constfold::fold_function)?;
+    println!("{}", folded.as_json());
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("{} operands replaced, {} instructions folded, {} branches folded",
+                  stats.operands, stats.folded, stats.branches);
+    }
+    Ok(())
+}
+
+// the report goes to stderr, as the program is printed
+fn dce(program: &Program, options: &Options) -> Result<(), String> {
+    let (reduced, report) = transform(program, options, dce::eliminate, dce::eliminate_function)?;
+    println!("{}", reduced.as_json());
+    if options.verbosity > Verbosity::Quiet {
+        eprint!("{}", report);
+    }
+    Ok(())
+}
+
+fn copyprop(program: &Program, options: &Options) -> Result<(), String> {
+    let scope = if options.switches.iter().any(|s| s == "--local") { Scope::Local } else { Scope::Global };
+    let (propagated, replaced) = transform(program, options, |p| copyprop::propagate(p, scope),
+                                           |p, f| copyprop::propagate_function(p, f, scope))?;
+    println!("{}", propagated.as_json());
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("{} reads replaced", replaced);
+    }
+    Ok(())
+}
+
+fn cse(program: &Program, options: &Options) -> Result<(), String> {
+    let (eliminated, replaced) = transform(program, options, cse::eliminate, cse::eliminate_function)?;
+    println!("{}", eliminated.as_json());
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("{} common subexpressions replaced by copies", replaced);
+    }
+    Ok(())
+}
+
+fn licm(program: &Program, options: &Options) -> Result<(), String> {
+    let (hoisted, moved) = transform(program, options, licm::hoist, licm::hoist_function)?;
+    println!("{}", hoisted.as_json());
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("{} loop-invariant instructions hoisted", moved);
+    }
+    Ok(())
+}
+
+fn inline(program: &Program, options: &Options) -> Result<(), String> {
+    let (inlined, calls) = transform(program, options, |p| inline::inline_calls(p, options.max_size),
+                                     |p, f| inline::inline_function(p, f, options.max_size))?;
+    println!("{}", inlined.as_json());
+    if options.verbosity == Verbosity::Verbose {
+        eprintln!("{} calls inlined", calls);
+    }
+    Ok(())
+}
+
+// the pipeline report goes to stderr with -v, as the program is printed
+fn opt(program: &Program, options: &Options) -> Result<(), String> {
+    if let Some(arg) = options.args.get(1) {
+        return Err(format!("unexpected argument {}", arg));
+    }
+    let mut manager = PassManager::new(options.passes.clone());
+    manager.fixpoint = options.switches.iter().any(|s| s == "--fixpoint");
+    let (optimized, report) = manager.run(program).map_err(|e| e.to_string().trim_end().to_string())?;
+    check_behaviour(program, &optimized, options)?;
+    println!("{}", optimized.as_json());
+    if options.verbosity == Verbosity::Verbose {
+        eprint!("{}", report);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let options = match parse_args(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            exit(2);
+        }
+    };
+    let generated = match options.command.as_str() {
+        "generate" => Some(generate(&options)),
+        "fuzz" => Some(fuzz(&options)),
+        _ => None,
+    };
+    if let Some(result) = generated {
+        if let Err(message) = result {
+            eprintln!("{}", message);
+            exit(1);
+        }
+        return;
+    }
+    let program = match load(&options) {
+        Ok(program) => program,
+        Err(message) => {
+            eprintln!("{}", message);
+            exit(1);
+        }
+    };
+    let result = match options.command.as_str() {
+        "stats" => {
+            print!("{}", stats::render(&program, options.format));
+            Ok(())
+        }
+        "validate" => validate(&program, &options),
+        "print" => print(&program, &options),
+        "run" => run(&program, &options),
+        "slice" => slice(&program, &options),
+        "reduce" => reduce(&program, &options),
+        "constfold" => constfold(&program, &options),
+        "dce" => dce(&program, &options),
+        "copyprop" => copyprop(&program, &options),
+        "cse" => cse(&program, &options),
+        "licm" => licm(&program, &options),
+        "inline" => inline(&program, &options),
+        "opt" => opt(&program, &options),
+        _ => analyze(&program, &options),
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        exit(1);
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::cfg::Cfg;
+use crate::lir::{*};
+use crate::reaching::{Effects, Memory, ProgramPoint};
+
+// live variables: a variable is live at a point if some path from there reads it before strongly
+// redefining it. weak definitions (stores and calls) do not kill, and globals and address-taken
+// variables are live when the function returns, since the caller may still read them.
+
+#[derive(Debug)]
+pub struct Liveness {
+    // the variables live right after every program point of the reachable blocks
+    pub live_after: HashMap<ProgramPoint, BTreeSet<String>>,
+    // the variables live at the start of every reachable block
+    pub live_in: HashMap<String, BTreeSet<String>>,
+    pub iterations: usize,
+}
+
+impl Liveness {
+    pub fn new(program: &Program, function: &Function) -> Liveness {
+        let memory = Memory::new(program, function);
+        let cfg = Cfg::new(function);
+        let at_exit: BTreeSet<String> = memory.call_reachable().into_iter().collect();
+
+        // effects of every point, in block order
+        let effects: HashMap<&String, Vec<Effects>> = cfg.rpo.iter().map(|bb_name| {
+            let bb = &function.body[bb_name];
+            let mut effects: Vec<Effects> = bb.insts.iter().map(|inst| Effects::of_inst(inst, &memory)).collect();
+            effects.push(Effects::of_term(&bb.term, &memory));
+            (bb_name, effects)
+        }).collect();
+
+        // live at the start of every block, solved backwards in postorder until nothing changes
+        let mut live_in: HashMap<&String, BTreeSet<String>> = cfg.rpo.iter().map(|bb| (bb, BTreeSet::new())).collect();
+        let mut iterations = 0;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for bb_name in cfg.rpo.iter().rev() {
+                iterations += 1;
+                let mut live = live_out(&cfg, bb_name, &live_in, &at_exit);
+                effects[bb_name].iter().rev().for_each(|e| transfer(&mut live, e));
+                if live != live_in[bb_name] {
+                    live_in.insert(bb_name, live);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut live_after = HashMap::new();
+        for bb_name in cfg.rpo.iter() {
+            let mut live = live_out(&cfg, bb_name, &live_in, &at_exit);
+            for (index, e) in effects[bb_name].iter().enumerate().rev() {
+                live_after.insert(ProgramPoint::new(&function.id, bb_name, index), live.clone());
+                transfer(&mut live, e);
+            }
+        }
+        let live_in = live_in.into_iter().map(|(bb, live)| (bb.clone(), live)).collect();
+        Liveness { live_after, live_in, iterations }
+    }
+
+    pub fn is_live_after(&self, point: &ProgramPoint, var: &str) -> bool {
+        self.live_after.get(point).is_some_and(|live| live.contains(var))
+    }
+}
+
+fn live_out(cfg: &Cfg, bb_name: &String, live_in: &HashMap<&String, BTreeSet<String>>,
+            at_exit: &BTreeSet<String>) -> BTreeSet<String> {
+    let succs = &cfg.succs[bb_name];
+    if succs.is_empty() {
+        return at_exit.clone();
+    }
+    succs.iter().flat_map(|s| live_in[s].iter().cloned()).collect()
+}
+
+fn transfer(live: &mut BTreeSet<String>, effects: &Effects) {
+    effects.strong_defs.iter().for_each(|v| { live.remove(v); });
+    live.extend(effects.uses.iter().cloned());
+}
@@ -0,0 +1,74 @@
+use std::fmt;
+use crate::congruence::{Congruence, Strided};
+use crate::domain::AbstractValue;
+use crate::lir::{*};
+use crate::zones::{Vars, Zone, Zones};
+
+// overflow diagnostics: every $arith whose exact result may not fit in an int, so that it wraps
+// around. the bounds of the operands come from the zones just before the $arith, so a counter
+// guarded by i < n is known not to wrap when incremented. an operand nothing is known about may be
+// any int, so p + 1, p / -1 and p + q may overflow on inputs p and q. one that is known to always
+// wrap, such as i32::MAX + 1 or i32::MIN / -1, overflows for certain.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverflowWarning {
+    // "function.block.index"
+    pub location: String,
+    pub inst: String,
+    // whether every execution of the $arith wraps, not just some
+    pub certain: bool,
+}
+
+impl fmt::Display for OverflowWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = if self.certain { "overflows" } else { "may overflow" };
+        write!(f, "warning: {}: {} {}", self.location, self.inst, what)
+    }
+}
+
+const MIN: i64 = i32::MIN as i64;
+const MAX: i64 = i32::MAX as i64;
+
+// the bounds of op in a closed zone; none for a variable the zones do not track
+fn bounds(zone: &Zone, vars: &Vars, op: &Operand) -> Option<Strided> {
+    let (lo, hi) = match op {
+        Operand::CInt(c) => (*c as i64, *c as i64),
+        Operand::Var(v) => zone.bounds(vars.index(&v.name)?),
+    };
+    Some(Strided::new(lo, hi, Congruence::top()))
+}
+
+// the $ariths of one function that may overflow, in block and instruction order
+pub fn overflow_warnings(program: &Program, function_name: &str) -> Vec<OverflowWarning> {
+    let function = &program.functions[function_name];
+    let zones = Zones::new(program, function_name);
+    let mut blocks: Vec<&String> = zones.entry.keys().collect();
+    blocks.sort();
+    let mut warnings = vec![];
+    for block in blocks {
+        let points = match zones.points(program, function_name, block) {
+            Some(points) => points,
+            None => continue,
+        };
+        // the zones run out at the first instruction no execution gets past
+        for ((index, inst), zone) in function.body[block].insts.iter().enumerate().zip(points.iter()) {
+            let (aop, op1, op2) = match inst {
+                Instruction::Arith { aop, op1, op2, .. } => (aop, op1, op2),
+                _ => continue,
+            };
+            let (a, b) = match (bounds(zone, &zones.vars, op1), bounds(zone, &zones.vars, op2)) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            match Strided::exact(&a, &b, aop) {
+                Some((lo, hi)) if lo < MIN || hi > MAX => warnings.push(OverflowWarning {
+                    location: format!("{}.{}.{}", function_name, block, index),
+                    inst: inst.to_string(),
+                    certain: hi < MIN || lo > MAX,
+                }),
+                _ => {}
+            }
+        }
+    }
+    warnings
+}
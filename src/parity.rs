@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use crate::domain::{self, AbstractValue};
+use crate::lir::{*};
+use crate::report::OutputFormat;
+
+// the parity analysis: whether an int is even or odd. parity survives the wrap-around of the
+// interpreter's arithmetic, as 2^32 is even, but says nothing about the result of a division.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+pub enum Parity {
+    Bottom,
+    Even,
+    Odd,
+    Top,
+}
+
+impl AbstractValue for Parity {
+    fn top() -> Self { Parity::Top }
+    fn bottom() -> Self { Parity::Bottom }
+
+    fn constant(value: i32) -> Self {
+        if value % 2 == 0 { Parity::Even } else { Parity::Odd }
+    }
+
+    fn join(a: &Self, b: &Self) -> Self {
+        match (a, b) {
+            (Parity::Bottom, _) => *b,
+            (_, Parity::Bottom) => *a,
+            _ if a == b => *a,
+            _ => Parity::Top,
+        }
+    }
+
+    fn arith(op1: &Self, op2: &Self, aop: &ArithOp) -> Self {
+        match (aop, op1, op2) {
+            (_, Parity::Bottom, _) | (_, _, Parity::Bottom) => Parity::Bottom,
+            (ArithOp::Add | ArithOp::Subtract, Parity::Top, _) |
+            (ArithOp::Add | ArithOp::Subtract, _, Parity::Top) => Parity::Top,
+            (ArithOp::Add | ArithOp::Subtract, a, b) => if a == b { Parity::Even } else { Parity::Odd },
+            (ArithOp::Multiply, Parity::Even, _) | (ArithOp::Multiply, _, Parity::Even) => Parity::Even,
+            (ArithOp::Multiply, Parity::Odd, Parity::Odd) => Parity::Odd,
+            _ => Parity::Top,
+        }
+    }
+
+    fn cmp(op1: &Self, op2: &Self, rop: &RelaOp) -> Self {
+        match (rop, op1, op2) {
+            (_, Parity::Bottom, _) | (_, _, Parity::Bottom) => Parity::Bottom,
+            // an even and an odd int are never equal
+            (RelaOp::Eq, Parity::Even, Parity::Odd) | (RelaOp::Eq, Parity::Odd, Parity::Even) => Parity::Even,
+            (RelaOp::Neq, Parity::Even, Parity::Odd) | (RelaOp::Neq, Parity::Odd, Parity::Even) => Parity::Odd,
+            _ => Parity::Top,
+        }
+    }
+
+    fn truth(&self) -> (bool, bool) {
+        match self {
+            Parity::Bottom => (false, false),
+            Parity::Odd => (true, false),
+            Parity::Even | Parity::Top => (true, true),
+        }
+    }
+
+    fn as_string(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+pub type AbstractStore = domain::AbstractStore<Parity>;
+
+// the parity of every int in every block the worklist reached, and how many blocks it processed
+pub fn parity_analysis(program: &Program, function_name: &str) -> (HashMap<String, AbstractStore>, usize) {
+    domain::analyze(program, function_name)
+}
+
+pub fn render(program: &Program, function_name: &str, format: OutputFormat) -> String {
+    domain::render::<Parity>("parity", program, function_name, format)
+}
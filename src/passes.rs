@@ -0,0 +1,149 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::copyprop::{self, Scope};
+use crate::lir::Program;
+use crate::stats::Stats;
+use crate::validate::{self, ValidationError};
+use crate::{constfold, cse, dce, inline, licm};
+
+// the pass manager: runs a pipeline of transforms such as "constfold,dce,copyprop" over every
+// function, validates the program after each pass, and optionally repeats the pipeline until a
+// round changes nothing. every pass is timed and its effect measured with Stats.
+
+// a transform of every function of a program, returning how many changes it made
+pub type Pass = fn(&Program) -> (Program, usize);
+
+pub const PASSES: [&str; 6] = ["constfold", "dce", "copyprop", "cse", "licm", "inline"];
+
+pub fn lookup(name: &str) -> Option<Pass> {
+    let pass: Pass = match name {
+        "constfold" => |p| {
+            let (p, stats) = constfold::fold(p);
+            (p, stats.operands + stats.folded + stats.branches)
+        },
+        "dce" => |p| {
+            let (p, report) = dce::eliminate(p);
+            (p, report.removed())
+        },
+        "copyprop" => |p| copyprop::propagate(p, Scope::Global),
+        "cse" => cse::eliminate,
+        "licm" => licm::hoist,
+        "inline" => inline::inline,
+        _ => return None,
+    };
+    Some(pass)
+}
+
+// the pass names of a comma separated pipeline, all of which must exist
+pub fn parse_pipeline(spec: &str) -> Result<Vec<String>, String> {
+    let names: Vec<String> = spec.split(',').map(|s| s.trim().to_string()).collect();
+    match names.iter().find(|name| lookup(name).is_none()) {
+        Some(name) if name.is_empty() => Err(format!("empty pass name in {}", spec)),
+        Some(name) => Err(format!("unknown pass {} (the passes are {})", name, PASSES.join(", "))),
+        None => Ok(names),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PassManager {
+    pub pipeline: Vec<String>,
+    // repeat the pipeline until a round leaves the program unchanged, at most max_rounds times
+    pub fixpoint: bool,
+    pub max_rounds: usize,
+}
+
+#[derive(Debug)]
+pub struct PassRun {
+    pub pass: String,
+    // from 1
+    pub round: usize,
+    pub elapsed: Duration,
+    pub changes: usize,
+    pub before: Stats,
+    pub after: Stats,
+}
+
+#[derive(Debug, Default)]
+pub struct PassReport {
+    pub runs: Vec<PassRun>,
+    pub rounds: usize,
+    // false when fixpoint was asked for but max_rounds ran out first
+    pub converged: bool,
+}
+
+// a pass that left the program ill formed
+#[derive(Debug)]
+pub struct PassError {
+    pub pass: String,
+    pub round: usize,
+    pub errors: Vec<ValidationError>,
+}
+
+impl fmt::Display for PassError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} (round {}) produced an invalid program:", self.pass, self.round)?;
+        self.errors.iter().try_for_each(|e| writeln!(f, "  {}", e))
+    }
+}
+
+impl std::error::Error for PassError {}
+
+fn delta(name: &str, before: u32, after: u32) -> String {
+    format!("{} {} -> {} ({:+})", name, before, after, after as i64 - before as i64)
+}
+
+impl fmt::Display for PassRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "round {} {}: {} changes in {:?}; {}, {}, {}", self.round, self.pass, self.changes, self.elapsed,
+               delta("instructions", self.before.instr_num, self.after.instr_num),
+               delta("blocks", self.before.block_num, self.after.block_num),
+               delta("locals", self.before.local_var_num, self.after.local_var_num))
+    }
+}
+
+impl fmt::Display for PassReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.runs.iter().try_for_each(|run| writeln!(f, "{}", run))?;
+        if let (Some(first), Some(last)) = (self.runs.first(), self.runs.last()) {
+            let total: Duration = self.runs.iter().map(|run| run.elapsed).sum();
+            writeln!(f, "{} round(s){} in {:?}; {}", self.rounds, if self.converged { "" } else { ", not converged" },
+                     total, delta("instructions", first.before.instr_num, last.after.instr_num))?;
+        }
+        Ok(())
+    }
+}
+
+impl PassManager {
+    pub fn new(pipeline: Vec<String>) -> PassManager {
+        PassManager { pipeline, fixpoint: false, max_rounds: 10 }
+    }
+
+    pub fn run(&self, program: &Program) -> Result<(Program, PassReport), PassError> {
+        let mut current = program.clone();
+        let mut report = PassReport::default();
+        let rounds = if self.fixpoint { self.max_rounds } else { 1 };
+        for round in 1..=rounds {
+            report.rounds = round;
+            let start_of_round = current.to_string();
+            for name in self.pipeline.iter() {
+                let pass = lookup(name).expect("pipelines are checked by parse_pipeline");
+                let before = current.get_stats();
+                let start = Instant::now();
+                let (next, changes) = pass(&current);
+                let elapsed = start.elapsed();
+                validate::validate(&next)
+                    .map_err(|errors| PassError { pass: name.clone(), round, errors })?;
+                report.runs.push(PassRun { pass: name.clone(), round, elapsed, changes, before, after: next.get_stats() });
+                current = next;
+            }
+            if current.to_string() == start_of_round {
+                report.converged = true;
+                break;
+            }
+        }
+        if !self.fixpoint {
+            report.converged = true;
+        }
+        Ok((current, report))
+    }
+}
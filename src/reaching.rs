@@ -0,0 +1,271 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use crate::lir::{*};
+use crate::cfg::Cfg;
+
+// a program point is an instruction of a block, or its terminal when index == insts.len()
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProgramPoint {
+    pub function: String,
+    pub block: String,
+    pub index: usize,
+}
+
+impl ProgramPoint {
+    pub fn new(function: &str, block: &str, index: usize) -> ProgramPoint {
+        ProgramPoint { function: function.to_string(), block: block.to_string(), index }
+    }
+
+    // parse the "block.index" form produced by Display
+    pub fn parse(function: &str, string: &str) -> Option<ProgramPoint> {
+        let (block, index) = string.rsplit_once('.')?;
+        Some(ProgramPoint::new(function, block, index.parse().ok()?))
+    }
+
+    pub fn site<'a>(&self, function: &'a Function) -> Option<Site<'a>> {
+        let bb = function.body.get(&self.block)?;
+        if self.index < bb.insts.len() {
+            Some(Site::Inst(&bb.insts[self.index]))
+        } else if self.index == bb.insts.len() {
+            Some(Site::Term(&bb.term))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ProgramPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.block, self.index)
+    }
+}
+
+// what lives at a program point
+#[derive(Clone, Copy, Debug)]
+pub enum Site<'a> {
+    Inst(&'a Instruction),
+    Term(&'a Terminal),
+}
+
+impl fmt::Display for Site<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Site::Inst(inst) => write!(f, "{}", inst),
+            Site::Term(term) => write!(f, "{}", term),
+        }
+    }
+}
+
+// variables whose address may be taken, so that loads, stores and calls reach them indirectly
+#[derive(Debug)]
+pub struct Memory {
+    pub globals: Vec<Variable>,
+    pub addr_taken: Vec<Variable>,
+}
+
+impl Memory {
+    pub fn new(program: &Program, function: &Function) -> Memory {
+        let global_names: HashSet<&String> = program.globals.iter().map(|g| &g.name).collect();
+        let mut addr_taken: Vec<Variable> = vec![];
+        // globals whose address is taken anywhere, locals whose address is taken in this function
+        program.functions.values().for_each(|f| {
+            f.body.values().flat_map(|bb| bb.insts.iter()).for_each(|inst| {
+                if let Instruction::AddrOf { rhs, .. } = inst {
+                    if global_names.contains(&rhs.name) || f.id == function.id {
+                        addr_taken.push(rhs.clone());
+                    }
+                }
+            });
+        });
+        addr_taken.sort();
+        addr_taken.dedup();
+        Memory { globals: program.globals.clone(), addr_taken }
+    }
+
+    // address-taken variables a pointer of the given type may point to
+    fn pointees(&self, ptr: &Type) -> Vec<String> {
+        self.addr_taken.iter()
+            .filter(|v| matches!(ptr, Type::Pointer(t) if **t == v.typ))
+            .map(|v| v.name.clone())
+            .collect()
+    }
+
+    // variables a callee may read or write
+    pub fn call_reachable(&self) -> Vec<String> {
+        let mut vars: Vec<String> = self.globals.iter().chain(self.addr_taken.iter())
+            .map(|v| v.name.clone())
+            .collect();
+        vars.sort();
+        vars.dedup();
+        vars
+    }
+}
+
+// variables read and written at a program point. strong definitions overwrite the variable,
+// weak ones (stores and calls) may or may not write it
+#[derive(Debug, Default)]
+pub struct Effects {
+    pub uses: Vec<String>,
+    pub strong_defs: Vec<String>,
+    pub weak_defs: Vec<String>,
+}
+
+fn operand_vars<'a>(ops: impl IntoIterator<Item = &'a Operand>) -> Vec<String> {
+    ops.into_iter().filter_map(|op| match op {
+        Operand::Var(v) => Some(v.name.clone()),
+        Operand::CInt(_) => None,
+    }).collect()
+}
+
+impl Effects {
+    pub fn of_inst(inst: &Instruction, memory: &Memory) -> Effects {
+        let mut effects = Effects::default();
+        match inst {
+            Instruction::AddrOf { lhs, .. } => {
+                effects.strong_defs.push(lhs.name.clone());
+            }
+            Instruction::Alloc { lhs, num, .. } => {
+                effects.uses = operand_vars([num]);
+                effects.strong_defs.push(lhs.name.clone());
+            }
+            Instruction::Copy { lhs, op } => {
+                effects.uses = operand_vars([op]);
+                effects.strong_defs.push(lhs.name.clone());
+            }
+            Instruction::Gep { lhs, src, idx } => {
+                effects.uses = operand_vars([idx]);
+                effects.uses.push(src.name.clone());
+                effects.strong_defs.push(lhs.name.clone());
+            }
+            Instruction::Arith { lhs, op1, op2, .. } |
+            Instruction::Cmp { lhs, op1, op2, .. } => {
+                effects.uses = operand_vars([op1, op2]);
+                effects.strong_defs.push(lhs.name.clone());
+            }
+            Instruction::Load { lhs, src } => {
+                effects.uses.push(src.name.clone());
+                effects.uses.extend(memory.pointees(&src.typ));
+                effects.strong_defs.push(lhs.name.clone());
+            }
+            Instruction::Store { dst, op } => {
+                effects.uses = operand_vars([op]);
+                effects.uses.push(dst.name.clone());
+                effects.weak_defs = memory.pointees(&dst.typ);
+            }
+            Instruction::Gfp { lhs, src, .. } => {
+                effects.uses.push(src.name.clone());
+                effects.strong_defs.push(lhs.name.clone());
+            }
+            Instruction::CallExt { lhs, args, .. } => {
+                effects.uses = operand_vars(args);
+                effects.uses.extend(memory.call_reachable());
+                effects.weak_defs = memory.call_reachable();
+                if let Some(lhs) = lhs {
+                    effects.strong_defs.push(lhs.name.clone());
+                }
+            }
+        }
+        effects.normalize();
+        effects
+    }
+
+    pub fn of_term(term: &Terminal, memory: &Memory) -> Effects {
+        let mut effects = Effects::default();
+        match term {
+            Terminal::Branch { cond, .. } => effects.uses = operand_vars([cond]),
+            Terminal::Ret(Some(op)) => effects.uses = operand_vars([op]),
+            Terminal::Jump(_) |
+            Terminal::Ret(None) => {}
+            Terminal::CallDirect { lhs, args, .. } |
+            Terminal::CallIndirect { lhs, args, .. } => {
+                effects.uses = operand_vars(args);
+                if let Terminal::CallIndirect { callee, .. } = term {
+                    effects.uses.push(callee.name.clone());
+                }
+                effects.uses.extend(memory.call_reachable());
+                effects.weak_defs = memory.call_reachable();
+                if let Some(lhs) = lhs {
+                    effects.strong_defs.push(lhs.name.clone());
+                }
+            }
+        }
+        effects.normalize();
+        effects
+    }
+
+    pub fn of_site(site: Site, memory: &Memory) -> Effects {
+        match site {
+            Site::Inst(inst) => Effects::of_inst(inst, memory),
+            Site::Term(term) => Effects::of_term(term, memory),
+        }
+    }
+
+    fn normalize(&mut self) {
+        self.uses.sort();
+        self.uses.dedup();
+        self.weak_defs.retain(|v| !self.strong_defs.contains(v));
+        self.weak_defs.sort();
+        self.weak_defs.dedup();
+    }
+}
+
+// definitions reaching a program point, keyed by variable
+pub type RdefStore = HashMap<String, BTreeSet<ProgramPoint>>;
+
+fn join(store: &mut RdefStore, store_to_join: &RdefStore) -> bool {
+    let mut changed = false;
+    for (var, defs) in store_to_join {
+        let old_defs = store.entry(var.clone()).or_default();
+        for def in defs {
+            changed |= old_defs.insert(def.clone());
+        }
+    }
+    changed
+}
+
+fn transfer(store: &mut RdefStore, effects: &Effects, point: &ProgramPoint) {
+    effects.strong_defs.iter().for_each(|v| {
+        store.insert(v.clone(), BTreeSet::from([point.clone()]));
+    });
+    effects.weak_defs.iter().for_each(|v| {
+        store.entry(v.clone()).or_default().insert(point.clone());
+    });
+}
+
+// reaching definitions of a function: the store right before every reachable program point,
+// along with the number of blocks the worklist processed
+pub fn reaching_defs(program: &Program, function_name: &str) -> (HashMap<ProgramPoint, RdefStore>, usize) {
+    let function = program.functions.get(function_name).unwrap();
+    let memory = Memory::new(program, function);
+    let cfg = Cfg::new(function);
+    let mut bb2store: HashMap<String, RdefStore> = HashMap::new();
+    let mut working_list = VecDeque::new();
+    let mut in_list: HashSet<String> = HashSet::new();
+    cfg.rpo.iter().for_each(|bb| {
+        bb2store.insert(bb.clone(), RdefStore::new());
+        working_list.push_back(bb.clone());
+        in_list.insert(bb.clone());
+    });
+
+    let mut point2store = HashMap::new();
+    let mut iterations = 0;
+    while let Some(bb_name) = working_list.pop_front() {
+        in_list.remove(&bb_name);
+        iterations += 1;
+        let bb = &function.body[&bb_name];
+        let mut current_store = bb2store[&bb_name].clone();
+        for index in 0..=bb.insts.len() {
+            let point = ProgramPoint::new(function_name, &bb_name, index);
+            let effects = Effects::of_site(point.site(function).unwrap(), &memory);
+            point2store.insert(point.clone(), current_store.clone());
+            transfer(&mut current_store, &effects, &point);
+        }
+        for target in cfg.succs[&bb_name].iter() {
+            let changed = join(bb2store.get_mut(target).unwrap(), &current_store);
+            if changed && in_list.insert(target.clone()) {
+                working_list.push_back(target.clone());
+            }
+        }
+    }
+    (point2store, iterations)
+}
@@ -0,0 +1,190 @@
+use std::collections::HashSet;
+use crate::cfg::{self, Cfg};
+use crate::lir::{*};
+use crate::validate;
+
+// delta debugging of lir programs: shrink a program while a predicate keeps holding, e.g. "the
+// fuzzer still reports this unsound constant". the reducer removes functions, rewrites terminals
+// into jumps and returns, drops the blocks that become unreachable, deletes runs of instructions
+// (halving their length as in ddmin) and finally unused locals, globals, externs and structs.
+// every candidate has to validate before the predicate sees it, and the passes are repeated until
+// none of them makes progress, so the result is minimal with respect to each single removal.
+
+#[derive(Debug)]
+pub struct Reduction {
+    pub program: Program,
+    // candidates that were well formed and given to the predicate, and the ones it kept
+    pub attempts: usize,
+    pub accepted: usize,
+}
+
+struct Reducer<F: FnMut(&Program) -> bool> {
+    current: Program,
+    interesting: F,
+    attempts: usize,
+    accepted: usize,
+}
+
+// what the reducer counts, and what reduction has to shrink
+pub fn size(program: &Program) -> usize {
+    program.functions.values()
+        .map(|f| 1 + f.locals.len() + f.body.values().map(|bb| 1 + bb.insts.len()).sum::<usize>())
+        .sum::<usize>()
+        + program.globals.len() + program.externs.len() + program.structs.len()
+}
+
+// the program reduced as far as interesting allows; a program that is not interesting to begin with
+// comes back unchanged
+pub fn reduce(program: &Program, interesting: impl FnMut(&Program) -> bool) -> Reduction {
+    let mut reducer = Reducer { current: program.clone(), interesting, attempts: 0, accepted: 0 };
+    if (reducer.interesting)(program) {
+        loop {
+            let before = size(&reducer.current);
+            reducer.functions();
+            reducer.terminals();
+            reducer.unreachable_blocks();
+            reducer.instructions();
+            reducer.declarations();
+            if size(&reducer.current) >= before {
+                break;
+            }
+        }
+    }
+    Reduction { program: reducer.current, attempts: reducer.attempts, accepted: reducer.accepted }
+}
+
+fn sorted<'a>(names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut names: Vec<String> = names.cloned().collect();
+    names.sort();
+    names
+}
+
+// a return that type checks in function, when one exists without new variables
+fn simplest_ret(function: &Function) -> Option<Terminal> {
+    match &function.ret_ty {
+        None => Some(Terminal::Ret(None)),
+        Some(Type::Int) => Some(Terminal::Ret(Some(Operand::CInt(0)))),
+        Some(typ) => function.params.iter().chain(function.locals.iter())
+            .find(|v| &v.typ == typ)
+            .map(|v| Terminal::Ret(Some(Operand::Var(v.clone())))),
+    }
+}
+
+impl<F: FnMut(&Program) -> bool> Reducer<F> {
+    // keep candidate if it is well formed and still interesting
+    fn try_candidate(&mut self, candidate: Program) -> bool {
+        if validate::validate(&candidate).is_err() {
+            return false;
+        }
+        self.attempts += 1;
+        if !(self.interesting)(&candidate) {
+            return false;
+        }
+        self.accepted += 1;
+        self.current = candidate;
+        true
+    }
+
+    fn functions(&mut self) {
+        for name in sorted(self.current.functions.keys()) {
+            let mut candidate = self.current.clone();
+            candidate.functions.remove(&name);
+            self.try_candidate(candidate);
+        }
+    }
+
+    // replace each terminal by a jump to one of its successors, or by a return
+    fn terminals(&mut self) {
+        for name in sorted(self.current.functions.keys()) {
+            for bb in sorted(self.current.functions[&name].body.keys()) {
+                let function = match self.current.functions.get(&name) {
+                    Some(function) if function.body.contains_key(&bb) => function,
+                    _ => continue,
+                };
+                let term = function.body[&bb].term.clone();
+                let mut replacements: Vec<Terminal> = vec![];
+                if !matches!(term, Terminal::Ret(_)) {
+                    replacements.extend(simplest_ret(function));
+                }
+                if !matches!(term, Terminal::Jump(_)) {
+                    replacements.extend(cfg::successors(&term).into_iter().map(Terminal::Jump));
+                }
+                for replacement in replacements {
+                    let mut candidate = self.current.clone();
+                    candidate.functions.get_mut(&name).unwrap().body.get_mut(&bb).unwrap().term = replacement;
+                    if self.try_candidate(candidate) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn unreachable_blocks(&mut self) {
+        for name in sorted(self.current.functions.keys()) {
+            let function = &self.current.functions[&name];
+            let reachable: HashSet<String> = Cfg::new(function).rpo.into_iter().collect();
+            if reachable.len() == function.body.len() {
+                continue;
+            }
+            let mut candidate = self.current.clone();
+            candidate.functions.get_mut(&name).unwrap().body.retain(|bb, _| reachable.contains(bb));
+            self.try_candidate(candidate);
+        }
+    }
+
+    // ddmin over the instructions of each block: try removing runs of length n/2, n/4, ..., 1
+    fn instructions(&mut self) {
+        for name in sorted(self.current.functions.keys()) {
+            for bb in sorted(self.current.functions[&name].body.keys()) {
+                let len = self.current.functions[&name].body[&bb].insts.len();
+                let mut chunk = len.div_ceil(2).max(1);
+                while chunk >= 1 && len > 0 {
+                    let mut start = 0;
+                    while start < self.current.functions[&name].body[&bb].insts.len() {
+                        let mut candidate = self.current.clone();
+                        let insts = &mut candidate.functions.get_mut(&name).unwrap().body.get_mut(&bb).unwrap().insts;
+                        let end = (start + chunk).min(insts.len());
+                        insts.drain(start..end);
+                        // on success the next run has moved to start
+                        if !self.try_candidate(candidate) {
+                            start += chunk;
+                        }
+                    }
+                    if chunk == 1 {
+                        break;
+                    }
+                    chunk = chunk.div_ceil(2);
+                }
+            }
+        }
+    }
+
+    // declarations nothing refers to any more; the validator rejects removing one that is still used
+    fn declarations(&mut self) {
+        for name in sorted(self.current.functions.keys()) {
+            let locals: Vec<String> = self.current.functions[&name].locals.iter().map(|v| v.name.clone()).collect();
+            for local in locals {
+                let mut candidate = self.current.clone();
+                candidate.functions.get_mut(&name).unwrap().locals.retain(|v| v.name != local);
+                self.try_candidate(candidate);
+            }
+        }
+        let globals: Vec<String> = self.current.globals.iter().map(|v| v.name.clone()).collect();
+        for global in globals {
+            let mut candidate = self.current.clone();
+            candidate.globals.retain(|v| v.name != global);
+            self.try_candidate(candidate);
+        }
+        for name in sorted(self.current.externs.keys()) {
+            let mut candidate = self.current.clone();
+            candidate.externs.remove(&name);
+            self.try_candidate(candidate);
+        }
+        for name in sorted(self.current.structs.keys()) {
+            let mut candidate = self.current.clone();
+            candidate.structs.remove(&name);
+            self.try_candidate(candidate);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+
+// machine-readable result of one analysis run on one function. every analysis shares this schema:
+//
+// {
+//   "analysis": "constants",          // constants | intervals | sign | parity | signparity | zones | congruence | sccp | rdef | control
+//   "function": "main",
+//   "iterations": 7,                  // blocks processed by the worklist, null if not iterative
+//   "blocks": {                       // reachable blocks only, sorted by name
+//     "bb1": { "x": "1", "y": "Top" }
+//   },
+//   "points": {                       // omitted unless the analysis has per-instruction facts
+//     "bb1.0": { "x": ["entry.0", "bb2.1"] }
+//   }
+// }
+//
+// a fact is a string for value analyses (the same text print_store shows) and a sorted list of
+// program points for rdef. for control, the keys of a block are the branch blocks it depends on
+// and the facts the branch outcome ("true" or "false") leading to it.
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub analysis: String,
+    pub function: String,
+    pub iterations: Option<usize>,
+    pub blocks: BTreeMap<String, BTreeMap<String, Fact>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub points: BTreeMap<String, BTreeMap<String, Fact>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Fact {
+    Value(String),
+    Points(Vec<String>),
+}
+
+impl Report {
+    pub fn new(analysis: &str, function: &str, iterations: Option<usize>) -> Report {
+        Report {
+            analysis: analysis.to_string(),
+            function: function.to_string(),
+            iterations,
+            blocks: BTreeMap::new(),
+            points: BTreeMap::new(),
+        }
+    }
+
+    pub fn as_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Dot,
+}
+
+// the value of --format: text, json or dot
+pub fn parse_format(name: &str) -> Option<OutputFormat> {
+    match name {
+        "text" => Some(OutputFormat::Text),
+        "json" => Some(OutputFormat::Json),
+        "dot" => Some(OutputFormat::Dot),
+        _ => None,
+    }
+}
@@ -0,0 +1,530 @@
+use crate::lir::{*};
+use crate::constants::IntConstAbsVal;
+use crate::domain::AbstractValue;
+use crate::cfg::{self, Cfg};
+use crate::dot;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use crate::report::{Fact, OutputFormat, Report};
+
+// sparse conditional constant propagation (Wegman and Zadeck).
+// the function is first put into ssa form: every int variable (params, locals and globals) gets a
+// fresh version at each definition and phi nodes are placed on the iterated dominance frontier.
+// calls and stores through pointers define new versions for the variables they may clobber, so
+// memory effects follow the same rules as the constants analysis.
+// the solver then tracks executable cfg edges and the values of ssa versions together, in the
+// lattice of the constants analysis, only joining phi arguments that flow along executable edges.
+// the function is entered along a virtual edge from START into the entry block, so that an entry
+// block with predecessors of its own merges the initial values with those of its back edges.
+
+// the virtual predecessor of the entry block
+const START: &str = "<start>";
+
+type SsaId = usize;
+
+#[derive(Debug)]
+struct Phi {
+    var: String,
+    lhs: SsaId,
+    // the version flowing in along the edge from each predecessor
+    args: Vec<(String, SsaId)>,
+}
+
+// ssa view of one basic block; instructions are not copied, only the versions they read and write
+#[derive(Debug, Default)]
+struct SsaBlock {
+    phis: Vec<Phi>,
+    // versions of the tracked variables read by each instruction
+    inst_uses: Vec<HashMap<String, SsaId>>,
+    // versions defined by each instruction, including clobbered variables
+    inst_defs: Vec<Vec<(String, SsaId)>>,
+    term_uses: HashMap<String, SsaId>,
+    term_defs: Vec<(String, SsaId)>,
+    // the version of every tracked variable live at the end of the block
+    exit: HashMap<String, SsaId>,
+}
+
+struct SsaFunction {
+    // original variable of every version
+    versions: Vec<String>,
+    // versions defined before entry: params and globals are Top, locals are uninitialized
+    entry_defs: Vec<(SsaId, IntConstAbsVal)>,
+    blocks: HashMap<String, SsaBlock>,
+}
+
+// variables that are read by an instruction or terminal
+fn used_vars(inst: &Instruction) -> Vec<&Variable> {
+    let operands: Vec<&Operand> = match inst {
+        Instruction::Copy { op, .. } |
+        Instruction::Store { op, .. } => vec![op],
+        Instruction::Arith { op1, op2, .. } |
+        Instruction::Cmp { op1, op2, .. } => vec![op1, op2],
+        Instruction::Alloc { num, .. } => vec![num],
+        Instruction::Gep { idx, .. } => vec![idx],
+        Instruction::CallExt { args, .. } => args.iter().collect(),
+        Instruction::AddrOf { .. } |
+        Instruction::Load { .. } |
+        Instruction::Gfp { .. } => vec![],
+    };
+    operands.into_iter().filter_map(|op| match op {
+        Operand::Var(v) => Some(v),
+        Operand::CInt(_) => None,
+    }).collect()
+}
+
+fn term_used_vars(term: &Terminal) -> Vec<&Variable> {
+    match term {
+        Terminal::Branch { cond: Operand::Var(v), .. } |
+        Terminal::Ret(Some(Operand::Var(v))) => vec![v],
+        _ => vec![],
+    }
+}
+
+struct Context {
+    // int globals, params and locals, the variables that get ssa versions
+    tracked: HashSet<String>,
+    global_ints: Vec<String>,
+    addr_taken_ints: Vec<String>,
+    global_ptr_to_ints: bool,
+}
+
+impl Context {
+    fn new(program: &Program, function: &Function) -> Context {
+        let mut tracked = HashSet::new();
+        let mut global_ints = vec![];
+        program.globals.iter().for_each(|g| {
+            if g.typ == Type::Int {
+                tracked.insert(g.name.clone());
+                global_ints.push(g.name.clone());
+            }
+        });
+        function.params.iter().chain(function.locals.iter()).for_each(|v| {
+            if v.typ == Type::Int {
+                tracked.insert(v.name.clone());
+            }
+        });
+        let mut addr_taken_ints: Vec<String> = function.body.values()
+            .flat_map(|bb| bb.insts.iter())
+            .filter_map(|inst| match inst {
+                Instruction::AddrOf { rhs, .. } if rhs.typ == Type::Int => Some(rhs.name.clone()),
+                _ => None,
+            })
+            .collect();
+        addr_taken_ints.sort();
+        addr_taken_ints.dedup();
+        global_ints.sort();
+        let global_ptr_to_ints = program.globals.iter().any(|g| g.typ.is_pointer_to_int());
+        Context { tracked, global_ints, addr_taken_ints, global_ptr_to_ints }
+    }
+
+    // variables a call may overwrite: the int lhs, every int global, and the address-taken ints
+    // if a pointer reaching an int escapes into the callee
+    fn call_defs(&self, lhs: &Option<Variable>, args: &[Operand]) -> Vec<String> {
+        let mut defs = self.global_ints.clone();
+        let any_arg_reaches_int = args.iter().any(|arg| match arg {
+            Operand::Var(v) => v.typ.is_pointer_to_int(),
+            Operand::CInt(_) => false,
+        });
+        if any_arg_reaches_int || self.global_ptr_to_ints {
+            defs.extend(self.addr_taken_ints.iter().cloned());
+        }
+        if let Some(lhs) = lhs {
+            if lhs.typ == Type::Int {
+                defs.push(lhs.name.clone());
+            }
+        }
+        defs.sort();
+        defs.dedup();
+        defs
+    }
+
+    fn inst_defs(&self, inst: &Instruction) -> Vec<String> {
+        match inst {
+            Instruction::Copy { lhs, .. } |
+            Instruction::Arith { lhs, .. } |
+            Instruction::Cmp { lhs, .. } |
+            Instruction::Load { lhs, .. } if self.tracked.contains(&lhs.name) => vec![lhs.name.clone()],
+            // a store through a pointer to int may write any address-taken int
+            Instruction::Store { dst, .. } if dst.typ.is_pointer_to_int() => self.addr_taken_ints.clone(),
+            Instruction::CallExt { lhs, args, .. } => self.call_defs(lhs, args),
+            _ => vec![],
+        }
+    }
+
+    // reads of a store include the old versions of the variables it weakly updates
+    fn inst_uses(&self, inst: &Instruction) -> Vec<String> {
+        let mut uses: Vec<String> = used_vars(inst).iter()
+            .map(|v| v.name.clone())
+            .filter(|name| self.tracked.contains(name))
+            .collect();
+        if let Instruction::Store { dst, .. } = inst {
+            if dst.typ.is_pointer_to_int() {
+                uses.extend(self.addr_taken_ints.iter().cloned());
+            }
+        }
+        uses
+    }
+
+    fn term_defs(&self, term: &Terminal) -> Vec<String> {
+        match term {
+            Terminal::CallDirect { lhs, args, .. } |
+            Terminal::CallIndirect { lhs, args, .. } => self.call_defs(lhs, args),
+            _ => vec![],
+        }
+    }
+}
+
+// build the ssa form with minimal phi placement and dominator tree renaming (Cytron et al.)
+fn build_ssa(program: &Program, function: &Function, cfg: &Cfg, ctx: &Context) -> SsaFunction {
+    // blocks defining each variable
+    let mut def_blocks: HashMap<String, HashSet<String>> = HashMap::new();
+    ctx.tracked.iter().for_each(|v| {
+        def_blocks.entry(v.clone()).or_default().insert(cfg.entry.clone());
+    });
+    for bb_name in cfg.rpo.iter() {
+        let bb = &function.body[bb_name];
+        bb.insts.iter().flat_map(|inst| ctx.inst_defs(inst))
+            .chain(ctx.term_defs(&bb.term))
+            .for_each(|v| { def_blocks.get_mut(&v).unwrap().insert(bb_name.clone()); });
+    }
+
+    // place phis on the iterated dominance frontier
+    let frontiers = cfg.dominance_frontiers();
+    let mut phi_vars: HashMap<String, Vec<String>> = HashMap::new();
+    let mut vars: Vec<&String> = ctx.tracked.iter().collect();
+    vars.sort();
+    for var in vars.iter() {
+        let mut has_phi: HashSet<String> = HashSet::new();
+        let mut work: Vec<String> = def_blocks[*var].iter().cloned().collect();
+        while let Some(bb) = work.pop() {
+            for frontier in frontiers[&bb].iter() {
+                if has_phi.insert(frontier.clone()) {
+                    phi_vars.entry(frontier.clone()).or_default().push((*var).clone());
+                    work.push(frontier.clone());
+                }
+            }
+        }
+    }
+    // the virtual edge from START makes entry a join point as soon as any block jumps back to it
+    if !cfg.preds[&cfg.entry].is_empty() {
+        phi_vars.insert(cfg.entry.clone(), vars.iter().map(|v| (*v).clone()).collect());
+    }
+
+    let mut ssa = SsaFunction { versions: vec![], entry_defs: vec![], blocks: HashMap::new() };
+    let mut stacks: HashMap<String, Vec<SsaId>> = HashMap::new();
+    let params: HashSet<&String> = function.params.iter().map(|p| &p.name).collect();
+    let globals: HashSet<&String> = program.globals.iter().map(|g| &g.name).collect();
+    for var in vars.iter() {
+        let id = ssa.versions.len();
+        ssa.versions.push((*var).clone());
+        let init = if params.contains(var) || globals.contains(var) {
+            IntConstAbsVal::top()
+        } else {
+            IntConstAbsVal::bottom()
+        };
+        ssa.entry_defs.push((id, init));
+        stacks.insert((*var).clone(), vec![id]);
+    }
+
+    // phis first so that renaming can fill in their arguments from any predecessor
+    for bb_name in cfg.rpo.iter() {
+        let mut block = SsaBlock::default();
+        for var in phi_vars.remove(bb_name).unwrap_or_default() {
+            let lhs = ssa.versions.len();
+            ssa.versions.push(var.clone());
+            // along the edge from START an entry phi takes the version defined before entry
+            let args = if bb_name == &cfg.entry { vec![(START.to_string(), stacks[&var][0])] } else { vec![] };
+            block.phis.push(Phi { var, lhs, args });
+        }
+        ssa.blocks.insert(bb_name.clone(), block);
+    }
+
+    // walk the dominator tree with an explicit stack; each entry remembers how many versions
+    // it pushed so they can be popped once the subtree is done
+    let dom_tree = cfg.dom_tree();
+    let mut walk: Vec<(String, bool)> = vec![(cfg.entry.clone(), false)];
+    let mut pushed_by: HashMap<String, Vec<String>> = HashMap::new();
+    while let Some((bb_name, done)) = walk.pop() {
+        if done {
+            for var in pushed_by.remove(&bb_name).unwrap_or_default() {
+                stacks.get_mut(&var).unwrap().pop();
+            }
+            continue;
+        }
+        let bb = &function.body[&bb_name];
+        let mut pushed = vec![];
+        let mut block = ssa.blocks.remove(&bb_name).unwrap();
+
+        for phi in block.phis.iter() {
+            stacks.get_mut(&phi.var).unwrap().push(phi.lhs);
+            pushed.push(phi.var.clone());
+        }
+        for inst in bb.insts.iter() {
+            let uses = ctx.inst_uses(inst).into_iter()
+                .map(|v| { let id = *stacks[&v].last().unwrap(); (v, id) })
+                .collect();
+            block.inst_uses.push(uses);
+            let mut defs = vec![];
+            for var in ctx.inst_defs(inst) {
+                let id = ssa.versions.len();
+                ssa.versions.push(var.clone());
+                stacks.get_mut(&var).unwrap().push(id);
+                pushed.push(var.clone());
+                defs.push((var, id));
+            }
+            block.inst_defs.push(defs);
+        }
+        block.term_uses = term_used_vars(&bb.term).iter()
+            .filter(|v| ctx.tracked.contains(&v.name))
+            .map(|v| (v.name.clone(), *stacks[&v.name].last().unwrap()))
+            .collect();
+        for var in ctx.term_defs(&bb.term) {
+            let id = ssa.versions.len();
+            ssa.versions.push(var.clone());
+            stacks.get_mut(&var).unwrap().push(id);
+            pushed.push(var.clone());
+            block.term_defs.push((var, id));
+        }
+        block.exit = stacks.iter().map(|(v, s)| (v.clone(), *s.last().unwrap())).collect();
+
+        for succ in cfg.succs[&bb_name].iter() {
+            if let Some(succ_block) = ssa.blocks.get_mut(succ) {
+                for phi in succ_block.phis.iter_mut() {
+                    phi.args.push((bb_name.clone(), *stacks[&phi.var].last().unwrap()));
+                }
+            } else if succ == &bb_name {
+                // self loop: the block itself has been taken out of the map
+                for phi in block.phis.iter_mut() {
+                    phi.args.push((bb_name.clone(), *stacks[&phi.var].last().unwrap()));
+                }
+            }
+        }
+        ssa.blocks.insert(bb_name.clone(), block);
+
+        pushed_by.insert(bb_name.clone(), pushed);
+        walk.push((bb_name.clone(), true));
+        for child in dom_tree[&bb_name].iter().rev() {
+            walk.push((child.clone(), false));
+        }
+    }
+    ssa
+}
+
+struct Solver<'a> {
+    function: &'a Function,
+    cfg: &'a Cfg,
+    ssa: &'a SsaFunction,
+    values: Vec<IntConstAbsVal>,
+    executable_edges: HashSet<(String, String)>,
+    executable_blocks: HashSet<String>,
+    flow_work: VecDeque<(String, String)>,
+    ssa_work: VecDeque<SsaId>,
+    // blocks whose phis, instructions or terminal read each version
+    users: HashMap<SsaId, HashSet<String>>,
+    // number of block visits, reported as the iteration count
+    visits: usize,
+}
+
+impl<'a> Solver<'a> {
+    fn new(function: &'a Function, cfg: &'a Cfg, ssa: &'a SsaFunction) -> Solver<'a> {
+        let mut values = vec![IntConstAbsVal::bottom(); ssa.versions.len()];
+        ssa.entry_defs.iter().for_each(|(id, init)| values[*id] = *init);
+        let mut users: HashMap<SsaId, HashSet<String>> = HashMap::new();
+        for (bb_name, block) in ssa.blocks.iter() {
+            let phi_uses = block.phis.iter().flat_map(|phi| phi.args.iter().map(|(_, id)| *id));
+            let inst_uses = block.inst_uses.iter().flat_map(|uses| uses.values().cloned());
+            let term_uses = block.term_uses.values().cloned();
+            phi_uses.chain(inst_uses).chain(term_uses).for_each(|id| {
+                users.entry(id).or_default().insert(bb_name.clone());
+            });
+        }
+        Solver {
+            function, cfg, ssa, values, users,
+            visits: 0,
+            executable_edges: HashSet::new(),
+            executable_blocks: HashSet::new(),
+            flow_work: VecDeque::new(),
+            ssa_work: VecDeque::new(),
+        }
+    }
+
+    fn solve(&mut self) {
+        self.executable_edges.insert((START.to_string(), self.cfg.entry.clone()));
+        self.executable_blocks.insert(self.cfg.entry.clone());
+        self.visit(&self.cfg.entry.clone());
+        loop {
+            if let Some((from, to)) = self.flow_work.pop_front() {
+                if !self.executable_edges.insert((from, to.clone())) { continue; }
+                self.executable_blocks.insert(to.clone());
+                self.visit(&to);
+            } else if let Some(id) = self.ssa_work.pop_front() {
+                let mut users: Vec<String> = self.users.get(&id).map(|u| u.iter().cloned().collect()).unwrap_or_default();
+                users.sort();
+                for bb_name in users {
+                    if self.executable_blocks.contains(&bb_name) {
+                        self.visit(&bb_name);
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn update(&mut self, id: SsaId, value: IntConstAbsVal) {
+        let new_value = AbstractValue::join(&self.values[id], &value);
+        if new_value != self.values[id] {
+            self.values[id] = new_value;
+            self.ssa_work.push_back(id);
+        }
+    }
+
+    fn resolve_operand(&self, uses: &HashMap<String, SsaId>, operand: &Operand) -> IntConstAbsVal {
+        match operand {
+            Operand::Var(v) => match uses.get(&v.name) {
+                Some(id) => self.values[*id],
+                // a non-int operand, mirror the dense analysis and give up on the result
+                None => IntConstAbsVal::top(),
+            },
+            Operand::CInt(i) => IntConstAbsVal::constant(*i),
+        }
+    }
+
+    // re-evaluate everything in a block that is known to be executable
+    fn visit(&mut self, bb_name: &String) {
+        self.visits += 1;
+        let ssa = self.ssa;
+        let block = &ssa.blocks[bb_name];
+        let bb = &self.function.body[bb_name];
+
+        for phi in block.phis.iter() {
+            let value = phi.args.iter()
+                .filter(|(pred, _)| self.executable_edges.contains(&(pred.clone(), bb_name.clone())))
+                .fold(IntConstAbsVal::bottom(), |acc, (_, id)| AbstractValue::join(&acc, &self.values[*id]));
+            self.update(phi.lhs, value);
+        }
+
+        for (i, inst) in bb.insts.iter().enumerate() {
+            let uses = &block.inst_uses[i];
+            let defs = &block.inst_defs[i];
+            match inst {
+                Instruction::Copy { op, .. } => {
+                    let value = self.resolve_operand(uses, op);
+                    defs.iter().for_each(|(_, id)| self.update(*id, value));
+                }
+                Instruction::Arith { op1, op2, aop, .. } => {
+                    let op1 = self.resolve_operand(uses, op1);
+                    let op2 = self.resolve_operand(uses, op2);
+                    let value = AbstractValue::arith(&op1, &op2, aop);
+                    defs.iter().for_each(|(_, id)| self.update(*id, value));
+                }
+                Instruction::Cmp { op1, op2, rop, .. } => {
+                    let op1 = self.resolve_operand(uses, op1);
+                    let op2 = self.resolve_operand(uses, op2);
+                    let value = AbstractValue::cmp(&op1, &op2, rop);
+                    defs.iter().for_each(|(_, id)| self.update(*id, value));
+                }
+                Instruction::Store { op, .. } => {
+                    // weak update: the stored value joins the previous value of each target
+                    let stored = self.resolve_operand(uses, op);
+                    defs.iter().for_each(|(var, id)| {
+                        let old = self.values[uses[var]];
+                        self.update(*id, AbstractValue::join(&old, &stored));
+                    });
+                }
+                _ => defs.iter().for_each(|(_, id)| self.update(*id, IntConstAbsVal::top())),
+            }
+        }
+        block.term_defs.iter().for_each(|(_, id)| self.update(*id, IntConstAbsVal::top()));
+
+        let targets: Vec<String> = match &bb.term {
+            Terminal::Branch { cond, tt, ff } => {
+                let (may_be_true, may_be_false) = self.resolve_operand(&block.term_uses, cond).truth();
+                let mut targets = vec![];
+                if may_be_true { targets.push(tt.clone()); }
+                if may_be_false { targets.push(ff.clone()); }
+                targets
+            }
+            term => cfg::successors(term),
+        };
+        for target in targets {
+            let edge = (bb_name.clone(), target);
+            if !self.executable_edges.contains(&edge) {
+                self.flow_work.push_back(edge);
+            }
+        }
+    }
+
+    // value of every tracked variable at the end of each executable block
+    fn exit_stores(&self) -> HashMap<String, HashMap<String, IntConstAbsVal>> {
+        self.executable_blocks.iter().map(|bb_name| {
+            let store = self.ssa.blocks[bb_name].exit.iter()
+                .map(|(var, id)| (var.clone(), self.values[*id]))
+                .collect();
+            (bb_name.clone(), store)
+        }).collect()
+    }
+}
+
+// per-block exit stores, plus the number of block visits the solver needed
+pub fn sccp_analysis(program: &Program, function_name: &str) -> (HashMap<String, HashMap<String, IntConstAbsVal>>, usize) {
+    let function = program.functions.get(function_name).unwrap();
+    let cfg = Cfg::new(function);
+    let ctx = Context::new(program, function);
+    let ssa = build_ssa(program, function, &cfg, &ctx);
+    let mut solver = Solver::new(function, &cfg, &ssa);
+    solver.solve();
+    (solver.exit_stores(), solver.visits)
+}
+
+// the non-bottom entries of every block's store as "var -> val" lines, sorted by variable
+fn store_lines(store: &HashMap<String, HashMap<String, IntConstAbsVal>>) -> HashMap<String, Vec<String>> {
+    store.iter().map(|(block, abs_store)| {
+        let mut keys: Vec<&String> = abs_store.keys().collect();
+        keys.sort();
+        let lines = keys.iter()
+            .map(|key| (key, abs_store[*key].as_string()))
+            .filter(|(_, val)| val != "Bottom")
+            .map(|(key, val)| format!("{} -> {}", key, val))
+            .collect();
+        (block.clone(), lines)
+    }).collect()
+}
+
+fn store_report(function_name: &str, store: &HashMap<String, HashMap<String, IntConstAbsVal>>, iterations: usize) -> Report {
+    let mut report = Report::new("sccp", function_name, Some(iterations));
+    report.blocks = store.iter().map(|(block, abs_store)| {
+        let facts: BTreeMap<String, Fact> = abs_store.iter()
+            .filter(|(_, val)| **val != IntConstAbsVal::bottom())
+            .map(|(key, val)| (key.clone(), Fact::Value(val.as_string())))
+            .collect();
+        (block.clone(), facts)
+    }).collect();
+    report
+}
+
+// same format as the constants analysis so that the two outputs can be diffed directly
+fn format_store(store: &HashMap<String, HashMap<String, IntConstAbsVal>>) -> String {
+    let lines = store_lines(store);
+    let mut blocks: Vec<&String> = lines.keys().collect();
+    blocks.sort();
+    let mut output = String::new();
+    for block in blocks {
+        output.push_str(&format!("{}:\n", block));
+        lines[block].iter().for_each(|line| output.push_str(&format!("{}\n", line)));
+        output.push('\n');
+    }
+    output
+}
+
+// run the analysis on one function and render the result in the requested format
+pub fn render(program: &Program, function_name: &str, format: OutputFormat) -> String {
+    let (store, iterations) = sccp_analysis(program, function_name);
+    match format {
+        OutputFormat::Text => format_store(&store),
+        OutputFormat::Json => format!("{}\n", store_report(function_name, &store, iterations).as_json()),
+        OutputFormat::Dot => {
+            let function = program.functions.get(function_name).unwrap();
+            dot::function_to_dot(function, &store_lines(&store))
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use crate::domain::{self, AbstractValue};
+use crate::lir::{*};
+use crate::report::OutputFormat;
+
+// the sign analysis: a cheaper alternative to intervals that only tracks whether an int may be
+// negative, zero or positive. the arithmetic wraps around as in the interpreter, so for instance
+// the sum of two positive ints may be negative, and a product of non-zero ints may be zero.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+pub enum Sign {
+    Bottom,
+    Neg,
+    Zero,
+    Pos,
+    NonPos,
+    NonZero,
+    NonNeg,
+    Top,
+}
+
+const NEG: u8 = 1;
+const ZERO: u8 = 2;
+const POS: u8 = 4;
+
+impl Sign {
+    // the set of signs as bits: 1 for negative, 2 for zero, 4 for positive
+    fn bits(&self) -> u8 {
+        match self {
+            Sign::Bottom => 0,
+            Sign::Neg => NEG,
+            Sign::Zero => ZERO,
+            Sign::Pos => POS,
+            Sign::NonPos => NEG | ZERO,
+            Sign::NonZero => NEG | POS,
+            Sign::NonNeg => ZERO | POS,
+            Sign::Top => NEG | ZERO | POS,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Sign {
+        match bits {
+            0 => Sign::Bottom,
+            NEG => Sign::Neg,
+            ZERO => Sign::Zero,
+            POS => Sign::Pos,
+            0b011 => Sign::NonPos,
+            0b101 => Sign::NonZero,
+            0b110 => Sign::NonNeg,
+            _ => Sign::Top,
+        }
+    }
+
+    // the signs of both
+    pub fn meet(&self, other: Sign) -> Sign {
+        Sign::from_bits(self.bits() & other.bits())
+    }
+
+    // the result of aop on an int of sign a and one of sign b, both single signs
+    fn arith_one(a: u8, b: u8, aop: &ArithOp) -> u8 {
+        const ALL: u8 = NEG | ZERO | POS;
+        match (aop, a, b) {
+            (ArithOp::Add, ZERO, s) | (ArithOp::Add, s, ZERO) => s,
+            (ArithOp::Add, POS, POS) => NEG | POS,
+            (ArithOp::Add, _, _) => ALL,
+            (ArithOp::Subtract, s, ZERO) => s,
+            (ArithOp::Subtract, ZERO, POS) => NEG,
+            // 0 - i32::MIN wraps to i32::MIN
+            (ArithOp::Subtract, ZERO, NEG) => NEG | POS,
+            (ArithOp::Subtract, NEG, POS) | (ArithOp::Subtract, POS, NEG) => NEG | POS,
+            (ArithOp::Subtract, _, _) => ALL,
+            (ArithOp::Multiply, ZERO, _) | (ArithOp::Multiply, _, ZERO) => ZERO,
+            (ArithOp::Multiply, _, _) => ALL,
+            // a division by zero faults; like the constant analysis, its result is unknown
+            (ArithOp::Divide, _, ZERO) => ALL,
+            (ArithOp::Divide, ZERO, _) => ZERO,
+            (ArithOp::Divide, POS, POS) => ZERO | POS,
+            (ArithOp::Divide, NEG, POS) | (ArithOp::Divide, POS, NEG) => NEG | ZERO,
+            // i32::MIN / -1 wraps to i32::MIN
+            (ArithOp::Divide, _, _) => ALL,
+        }
+    }
+
+    // the orderings possible between an int of sign a and one of sign b, both single signs
+    fn orderings(a: u8, b: u8) -> Vec<std::cmp::Ordering> {
+        use std::cmp::Ordering::{Equal, Greater, Less};
+        match (a, b) {
+            (ZERO, ZERO) => vec![Equal],
+            _ if a == b => vec![Less, Equal, Greater],
+            _ if a < b => vec![Less],
+            _ => vec![Greater],
+        }
+    }
+}
+
+fn singles(bits: u8) -> impl Iterator<Item = u8> {
+    [NEG, ZERO, POS].into_iter().filter(move |s| bits & s != 0)
+}
+
+impl AbstractValue for Sign {
+    fn top() -> Self { Sign::Top }
+    fn bottom() -> Self { Sign::Bottom }
+
+    fn constant(value: i32) -> Self {
+        match value {
+            0 => Sign::Zero,
+            v if v < 0 => Sign::Neg,
+            _ => Sign::Pos,
+        }
+    }
+
+    fn join(a: &Self, b: &Self) -> Self {
+        Sign::from_bits(a.bits() | b.bits())
+    }
+
+    fn arith(op1: &Self, op2: &Self, aop: &ArithOp) -> Self {
+        let mut bits = 0;
+        for a in singles(op1.bits()) {
+            for b in singles(op2.bits()) {
+                bits |= Sign::arith_one(a, b, aop);
+            }
+        }
+        Sign::from_bits(bits)
+    }
+
+    fn cmp(op1: &Self, op2: &Self, rop: &RelaOp) -> Self {
+        let (mut may_be_true, mut may_be_false) = (false, false);
+        for a in singles(op1.bits()) {
+            for b in singles(op2.bits()) {
+                for ordering in Sign::orderings(a, b) {
+                    let holds = match rop {
+                        RelaOp::Eq => ordering.is_eq(),
+                        RelaOp::Neq => ordering.is_ne(),
+                        RelaOp::Less => ordering.is_lt(),
+                        RelaOp::LessEq => ordering.is_le(),
+                        RelaOp::Greater => ordering.is_gt(),
+                        RelaOp::GreaterEq => ordering.is_ge(),
+                    };
+                    may_be_true |= holds;
+                    may_be_false |= !holds;
+                }
+            }
+        }
+        match (may_be_true, may_be_false) {
+            (true, true) => Sign::NonNeg,
+            (true, false) => Sign::Pos,
+            (false, true) => Sign::Zero,
+            (false, false) => Sign::Bottom,
+        }
+    }
+
+    fn truth(&self) -> (bool, bool) {
+        (self.bits() & (NEG | POS) != 0, self.bits() & ZERO != 0)
+    }
+
+    fn as_string(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+pub type AbstractStore = domain::AbstractStore<Sign>;
+
+// the sign of every int in every block the worklist reached, and how many blocks it processed
+pub fn sign_analysis(program: &Program, function_name: &str) -> (HashMap<String, AbstractStore>, usize) {
+    domain::analyze(program, function_name)
+}
+
+pub fn render(program: &Program, function_name: &str, format: OutputFormat) -> String {
+    domain::render::<Sign>("sign", program, function_name, format)
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use crate::domain::{self, AbstractValue};
+use crate::lir::{*};
+use crate::parity::Parity;
+use crate::report::OutputFormat;
+use crate::sign::Sign;
+
+// the product of the sign and parity analyses, run as one. the reduced product lets each side
+// sharpen the other after every step: zero is even, an odd int is not zero, and a value either side
+// rules out entirely is bottom on both. the plain product keeps the two sides independent.
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+pub struct SignParity<const REDUCED: bool> {
+    pub sign: Sign,
+    pub parity: Parity,
+}
+
+pub type Product = SignParity<false>;
+pub type ReducedProduct = SignParity<true>;
+
+impl<const REDUCED: bool> SignParity<REDUCED> {
+    pub fn new(sign: Sign, parity: Parity) -> Self {
+        let value = SignParity { sign, parity };
+        if REDUCED { value.reduce() } else { value }
+    }
+
+    fn reduce(self) -> Self {
+        let SignParity { mut sign, mut parity } = self;
+        if sign == Sign::Zero {
+            parity = match parity {
+                Parity::Odd | Parity::Bottom => Parity::Bottom,
+                Parity::Even | Parity::Top => Parity::Even,
+            };
+        }
+        if parity == Parity::Odd {
+            sign = sign.meet(Sign::NonZero);
+        }
+        if sign == Sign::Bottom || parity == Parity::Bottom {
+            return SignParity { sign: Sign::Bottom, parity: Parity::Bottom };
+        }
+        SignParity { sign, parity }
+    }
+}
+
+impl<const REDUCED: bool> AbstractValue for SignParity<REDUCED> {
+    fn top() -> Self { SignParity::new(Sign::Top, Parity::Top) }
+    fn bottom() -> Self { SignParity::new(Sign::Bottom, Parity::Bottom) }
+    fn constant(value: i32) -> Self { SignParity::new(Sign::constant(value), Parity::constant(value)) }
+
+    fn join(a: &Self, b: &Self) -> Self {
+        SignParity::new(Sign::join(&a.sign, &b.sign), Parity::join(&a.parity, &b.parity))
+    }
+
+    fn arith(op1: &Self, op2: &Self, aop: &ArithOp) -> Self {
+        SignParity::new(Sign::arith(&op1.sign, &op2.sign, aop), Parity::arith(&op1.parity, &op2.parity, aop))
+    }
+
+    fn cmp(op1: &Self, op2: &Self, rop: &RelaOp) -> Self {
+        SignParity::new(Sign::cmp(&op1.sign, &op2.sign, rop), Parity::cmp(&op1.parity, &op2.parity, rop))
+    }
+
+    // a branch can only go where both sides allow
+    fn truth(&self) -> (bool, bool) {
+        let (sign_true, sign_false) = self.sign.truth();
+        let (parity_true, parity_false) = self.parity.truth();
+        (sign_true && parity_true, sign_false && parity_false)
+    }
+
+    fn as_string(&self) -> String {
+        format!("({}, {})", self.sign.as_string(), self.parity.as_string())
+    }
+}
+
+pub type AbstractStore<const REDUCED: bool> = domain::AbstractStore<SignParity<REDUCED>>;
+
+// the sign and parity of every int in every block the worklist reached, and how many blocks it
+// processed
+pub fn sign_parity_analysis<const REDUCED: bool>(program: &Program, function_name: &str)
+                                                 -> (HashMap<String, AbstractStore<REDUCED>>, usize) {
+    domain::analyze(program, function_name)
+}
+
+pub fn render(program: &Program, function_name: &str, format: OutputFormat) -> String {
+    domain::render::<Product>("signparity", program, function_name, format)
+}
+
+pub fn render_reduced(program: &Program, function_name: &str, format: OutputFormat) -> String {
+    domain::render::<ReducedProduct>("signparity", program, function_name, format)
+}
@@ -0,0 +1,108 @@
+use std::collections::{BTreeSet, HashMap};
+use crate::lir::{*};
+use crate::cfg::Cfg;
+use crate::reaching::ProgramPoint;
+use crate::defuse::DefUse;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Backward,
+    Forward,
+}
+
+// static slicer over the program dependence relations of one function: data dependences come
+// from def-use chains, control dependences from post-dominance
+pub struct Slicer<'a> {
+    function: &'a Function,
+    chains: DefUse,
+    // block -> blocks whose branch decides whether it runs
+    control_deps: HashMap<String, BTreeSet<String>>,
+    // block -> blocks whose execution it decides
+    control_dependents: HashMap<String, BTreeSet<String>>,
+}
+
+impl<'a> Slicer<'a> {
+    pub fn new(program: &'a Program, function_name: &str) -> Slicer<'a> {
+        let function = program.functions.get(function_name).unwrap();
+        let chains = DefUse::new(program, function_name);
+        let control_deps = Cfg::new(function).control_deps();
+        let mut control_dependents: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for (bb, deps) in control_deps.iter() {
+            for dep in deps {
+                control_dependents.entry(dep.clone()).or_default().insert(bb.clone());
+            }
+        }
+        Slicer { function, chains, control_deps, control_dependents }
+    }
+
+    fn terminal(&self, bb: &str) -> ProgramPoint {
+        ProgramPoint::new(&self.function.id, bb, self.function.body[bb].insts.len())
+    }
+
+    fn points_of(&self, bb: &str) -> impl Iterator<Item = ProgramPoint> + '_ {
+        let function = &self.function.id;
+        let bb = bb.to_string();
+        (0..=self.function.body[&bb].insts.len()).map(move |i| ProgramPoint::new(function, &bb, i))
+    }
+
+    // program points the given point directly depends on
+    fn dependences(&self, point: &ProgramPoint) -> Vec<ProgramPoint> {
+        let mut deps: Vec<ProgramPoint> = self.chains.all_reaching(point).into_iter().cloned().collect();
+        if let Some(branches) = self.control_deps.get(&point.block) {
+            deps.extend(branches.iter().map(|bb| self.terminal(bb)));
+        }
+        deps
+    }
+
+    // program points directly depending on the given point
+    fn dependents(&self, point: &ProgramPoint) -> Vec<ProgramPoint> {
+        let mut deps: Vec<ProgramPoint> = self.chains.all_uses(point).into_iter().cloned().collect();
+        if *point == self.terminal(&point.block) {
+            if let Some(blocks) = self.control_dependents.get(&point.block) {
+                deps.extend(blocks.iter().flat_map(|bb| self.points_of(bb)));
+            }
+        }
+        deps
+    }
+
+    // slice with respect to var at criterion. a backward slice holds every point that may affect
+    // the value of var read at criterion, a forward slice every point the value of var defined at
+    // criterion may affect. the criterion itself is always part of the slice.
+    pub fn slice(&self, criterion: &ProgramPoint, var: &str, direction: Direction) -> BTreeSet<ProgramPoint> {
+        let mut slice = BTreeSet::from([criterion.clone()]);
+        let mut working_list: Vec<ProgramPoint> = match direction {
+            Direction::Backward => {
+                let mut start: Vec<ProgramPoint> = self.chains.reaching(criterion, var).cloned().collect();
+                if let Some(branches) = self.control_deps.get(&criterion.block) {
+                    start.extend(branches.iter().map(|bb| self.terminal(bb)));
+                }
+                start
+            }
+            Direction::Forward => self.chains.uses_of(criterion, var).cloned().collect(),
+        };
+        while let Some(point) = working_list.pop() {
+            if !slice.insert(point.clone()) { continue; }
+            let next = match direction {
+                Direction::Backward => self.dependences(&point),
+                Direction::Forward => self.dependents(&point),
+            };
+            working_list.extend(next.into_iter().filter(|p| !slice.contains(p)));
+        }
+        slice
+    }
+
+    // the program with every instruction of the function outside the slice removed. blocks and
+    // terminals are kept as they are so that the result is still a well-formed cfg.
+    pub fn sliced_program(&self, program: &Program, slice: &BTreeSet<ProgramPoint>) -> Program {
+        let mut sliced = program.clone();
+        let function = sliced.functions.get_mut(&self.function.id).unwrap();
+        for (bb_name, bb) in function.body.iter_mut() {
+            let insts = std::mem::take(&mut bb.insts);
+            bb.insts = insts.into_iter().enumerate()
+                .filter(|(i, _)| slice.contains(&ProgramPoint::new(&self.function.id, bb_name, *i)))
+                .map(|(_, inst)| inst)
+                .collect();
+        }
+        sliced
+    }
+}
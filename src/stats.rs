@@ -1,8 +1,15 @@
-use std::fs::File;
-use std::io::{self, BufRead, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Result};
 use std::path::Path;
+use serde::Serialize;
+use crate::cfg::Cfg;
+use crate::lir::{*};
+use crate::report::OutputFormat;
+use crate::types::{TypeCensus, TypeHistogram};
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Debug, PartialEq)]
 pub struct Stats {
     pub field_num: u32,
     pub function_returning_value_num: u32,
@@ -19,6 +26,53 @@ pub struct Stats {
     pub pointer_to_pointer_num: u32,
 }
 
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+// the description of every field in a .stats file, in the order the fields are written
+const DESCRIPTIONS: [&str; 13] = [
+    "Number of fields across all struct types",
+    "Number of functions that return a value",
+    "Number of function parameters",
+    "Number of local variables",
+    "Number of basic blocks",
+    "Number of instructions",
+    "Number of terminals",
+    "Number of locals and globals with int type",
+    "Number of locals and globals with struct type",
+    "Number of locals and globals with pointer to int type",
+    "Number of locals and globals with pointer to struct type",
+    "Number of locals and globals with pointer to function type",
+    "Number of locals and globals with pointer to pointer type",
+];
+
+// the same "description: number" lines that from_file reads back
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (description, value) in DESCRIPTIONS.iter().zip(self.values()) {
+            writeln!(f, "{}: {}", description, value)?;
+        }
+        Ok(())
+    }
+}
+
+// the problems that kept a .stats file from being read, one per line of the file or missing field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsParseError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for StatsParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.problems.join("\n"))
+    }
+}
+
+impl std::error::Error for StatsParseError {}
+
 impl Stats {
     pub fn new()-> Stats {
         Stats {
@@ -38,47 +92,234 @@ impl Stats {
         }
     }
 
+    // field values in DESCRIPTIONS order
+    fn values(&self) -> [u32; 13] {
+        [
+            self.field_num,
+            self.function_returning_value_num,
+            self.func_param_num,
+            self.local_var_num,
+            self.block_num,
+            self.instr_num,
+            self.terminal_num,
+            self.int_type_num,
+            self.struct_type_num,
+            self.pointer_to_int_num,
+            self.pointer_to_struct_num,
+            self.pointer_to_function_num,
+            self.pointer_to_pointer_num,
+        ]
+    }
+
+    fn field_mut(&mut self, description: &str) -> Option<&mut u32> {
+        let index = DESCRIPTIONS.iter().position(|d| *d == description)?;
+        Some(match index {
+            0 => &mut self.field_num,
+            1 => &mut self.function_returning_value_num,
+            2 => &mut self.func_param_num,
+            3 => &mut self.local_var_num,
+            4 => &mut self.block_num,
+            5 => &mut self.instr_num,
+            6 => &mut self.terminal_num,
+            7 => &mut self.int_type_num,
+            8 => &mut self.struct_type_num,
+            9 => &mut self.pointer_to_int_num,
+            10 => &mut self.pointer_to_struct_num,
+            11 => &mut self.pointer_to_function_num,
+            _ => &mut self.pointer_to_pointer_num,
+        })
+    }
+
+    // read "description: number" lines; blank lines are skipped, and unknown, malformed or repeated
+    // lines as well as missing fields are all reported
+    pub fn parse(text: &str) -> std::result::Result<Stats, StatsParseError> {
+        let mut stats = Stats::new();
+        let mut seen: Vec<&str> = vec![];
+        let mut problems = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (description, number) = match line.rsplit_once(':') {
+                Some((description, number)) => (description.trim(), number.trim()),
+                None => {
+                    problems.push(format!("line {}: expected \"description: number\" but found \"{}\"", i + 1, line));
+                    continue;
+                }
+            };
+            let number = match number.parse::<u32>() {
+                Ok(number) => number,
+                Err(_) => {
+                    problems.push(format!("line {}: \"{}\" is not a count", i + 1, number));
+                    continue;
+                }
+            };
+            if seen.contains(&description) {
+                problems.push(format!("line {}: \"{}\" is given more than once", i + 1, description));
+                continue;
+            }
+            match stats.field_mut(description) {
+                Some(field) => *field = number,
+                None => {
+                    problems.push(format!("line {}: unknown statistic \"{}\"", i + 1, description));
+                    continue;
+                }
+            }
+            seen.push(description);
+        }
+        DESCRIPTIONS.iter()
+            .filter(|d| !seen.contains(d))
+            .for_each(|d| problems.push(format!("missing \"{}\"", d)));
+        if problems.is_empty() { Ok(stats) } else { Err(StatsParseError { problems }) }
+    }
+
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Stats> {
-        let file = File::open(path)?;
-        let reader = io::BufReader::new(file);
-
-        let mut stats = Stats::new();  // Assuming Stats::new() initializes all fields to 0
-
-
-
-        for line in reader.lines() {
-            let line = line?;
-            if let Some(number) = line.split(": ").nth(1).and_then(|s| s.parse::<u32>().ok()) {
-                if line.contains("Number of fields across all struct types") {
-                    stats.field_num = number;
-                } else if line.contains("Number of functions that return a value") {
-                    stats.function_returning_value_num = number;
-                } else if line.contains("Number of function parameters") {
-                    stats.func_param_num = number;
-                } else if line.contains("Number of local variables") {
-                    stats.local_var_num = number;
-                } else if line.contains("Number of basic blocks") {
-                    stats.block_num = number;
-                } else if line.contains("Number of instructions") {
-                    stats.instr_num = number;
-                } else if line.contains("Number of terminals") {
-                    stats.terminal_num = number;
-                } else if line.contains("Number of locals and globals with int type") {
-                    stats.int_type_num = number;
-                } else if line.contains("Number of locals and globals with struct type") {
-                    stats.struct_type_num = number;
-                } else if line.contains("Number of locals and globals with pointer to int type") {
-                    stats.pointer_to_int_num = number;
-                } else if line.contains("Number of locals and globals with pointer to struct type") {
-                    stats.pointer_to_struct_num = number;
-                } else if line.contains("Number of locals and globals with pointer to function type") {
-                    stats.pointer_to_function_num = number;
-                } else if line.contains("Number of locals and globals with pointer to pointer type") {
-                    stats.pointer_to_pointer_num = number;
+        let text = fs::read_to_string(path)?;
+        Stats::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+const INSTRUCTION_OPCODES: [&str; 10] = ["addrof", "alloc", "copy", "gep", "arith", "load", "store", "gfp", "cmp", "call_ext"];
+const TERMINAL_OPCODES: [&str; 5] = ["jump", "branch", "ret", "call_dir", "call_idr"];
+
+// control flow metrics of a single function
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct FunctionMetrics {
+    pub blocks: u32,
+    pub cyclomatic_complexity: u32,
+    pub loops: u32,
+    // 0 without loops, 1 when no loop is nested in another
+    pub max_loop_depth: u32,
+    pub unreachable_blocks: Vec<String>,
+}
+
+// metrics about the code of a program, beyond the declaration counts of Stats
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct Metrics {
+    // instructions and terminals per opcode, every opcode present
+    pub instructions: BTreeMap<String, u32>,
+    pub terminals: BTreeMap<String, u32>,
+    pub direct_calls: u32,
+    pub indirect_calls: u32,
+    pub external_calls: u32,
+    pub max_blocks_per_function: u32,
+    pub avg_blocks_per_function: f64,
+    pub loops: u32,
+    pub max_loop_depth: u32,
+    pub unreachable_blocks: u32,
+    // variables whose address is taken, "function.var" for params and locals
+    pub address_taken: Vec<String>,
+    pub functions: BTreeMap<String, FunctionMetrics>,
+}
+
+impl Metrics {
+    pub fn new(program: &Program) -> Metrics {
+        let mut metrics = Metrics::default();
+        INSTRUCTION_OPCODES.iter().for_each(|op| { metrics.instructions.insert(op.to_string(), 0); });
+        TERMINAL_OPCODES.iter().for_each(|op| { metrics.terminals.insert(op.to_string(), 0); });
+        let mut address_taken = BTreeSet::new();
+
+        for (name, function) in program.functions.iter() {
+            let is_local = |var: &str| function.params.iter().chain(function.locals.iter()).any(|v| v.name == var);
+            for bb in function.body.values() {
+                for inst in bb.insts.iter() {
+                    *metrics.instructions.get_mut(inst.opcode()).unwrap() += 1;
+                    match inst {
+                        Instruction::CallExt { .. } => metrics.external_calls += 1,
+                        Instruction::AddrOf { rhs, .. } if is_local(&rhs.name) => {
+                            address_taken.insert(format!("{}.{}", name, rhs.name));
+                        }
+                        Instruction::AddrOf { rhs, .. } => { address_taken.insert(rhs.name.clone()); }
+                        _ => {}
+                    }
                 }
+                *metrics.terminals.get_mut(bb.term.opcode()).unwrap() += 1;
+                match bb.term {
+                    Terminal::CallDirect { .. } => metrics.direct_calls += 1,
+                    Terminal::CallIndirect { .. } => metrics.indirect_calls += 1,
+                    _ => {}
+                }
+            }
+
+            let mut function_metrics = FunctionMetrics { blocks: function.body.len() as u32, ..FunctionMetrics::default() };
+            if function.body.contains_key("entry") {
+                let cfg = Cfg::new(function);
+                function_metrics.cyclomatic_complexity = cfg.cyclomatic_complexity() as u32;
+                let depths = cfg.loop_depths();
+                function_metrics.loops = depths.len() as u32;
+                function_metrics.max_loop_depth = depths.values().max().copied().unwrap_or(0) as u32;
+                let mut unreachable: Vec<String> = function.body.keys().filter(|bb| !cfg.preds.contains_key(*bb)).cloned().collect();
+                unreachable.sort();
+                function_metrics.unreachable_blocks = unreachable;
             }
+            metrics.loops += function_metrics.loops;
+            metrics.max_loop_depth = metrics.max_loop_depth.max(function_metrics.max_loop_depth);
+            metrics.unreachable_blocks += function_metrics.unreachable_blocks.len() as u32;
+            metrics.max_blocks_per_function = metrics.max_blocks_per_function.max(function_metrics.blocks);
+            metrics.functions.insert(name.clone(), function_metrics);
         }
+        if !program.functions.is_empty() {
+            let blocks: u32 = metrics.functions.values().map(|f| f.blocks).sum();
+            metrics.avg_blocks_per_function = blocks as f64 / program.functions.len() as f64;
+        }
+        metrics.address_taken = address_taken.into_iter().collect();
+        metrics
+    }
+}
 
-        Ok(stats)
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Instructions by opcode:")?;
+        for (op, n) in self.instructions.iter() {
+            writeln!(f, "  {}: {}", op, n)?;
+        }
+        writeln!(f, "Terminals by opcode:")?;
+        for (op, n) in self.terminals.iter() {
+            writeln!(f, "  {}: {}", op, n)?;
+        }
+        writeln!(f, "Number of direct calls: {}", self.direct_calls)?;
+        writeln!(f, "Number of indirect calls: {}", self.indirect_calls)?;
+        writeln!(f, "Number of external calls: {}", self.external_calls)?;
+        writeln!(f, "Maximum basic blocks per function: {}", self.max_blocks_per_function)?;
+        writeln!(f, "Average basic blocks per function: {:.2}", self.avg_blocks_per_function)?;
+        writeln!(f, "Number of loops: {}", self.loops)?;
+        writeln!(f, "Maximum loop nesting depth: {}", self.max_loop_depth)?;
+        writeln!(f, "Number of unreachable basic blocks: {}", self.unreachable_blocks)?;
+        writeln!(f, "Address-taken variables: {}", or_none(&self.address_taken))?;
+        for (name, function) in self.functions.iter() {
+            writeln!(f, "Function {}:", name)?;
+            writeln!(f, "  basic blocks: {}", function.blocks)?;
+            writeln!(f, "  cyclomatic complexity: {}", function.cyclomatic_complexity)?;
+            writeln!(f, "  loops: {}", function.loops)?;
+            writeln!(f, "  maximum loop nesting depth: {}", function.max_loop_depth)?;
+            writeln!(f, "  unreachable blocks: {}", or_none(&function.unreachable_blocks))?;
+        }
+        Ok(())
+    }
+}
+
+fn or_none(names: &[String]) -> String {
+    if names.is_empty() { "none".to_string() } else { names.join(", ") }
+}
+
+// the declaration counts, the code metrics and the type histogram, as text or as
+// {"stats": .., "metrics": .., "types": ..}
+pub fn render(program: &Program, format: OutputFormat) -> String {
+    let stats = program.get_stats();
+    let metrics = program.get_metrics();
+    let types = TypeCensus::new(program).histogram();
+    match format {
+        OutputFormat::Json => {
+            #[derive(Serialize)]
+            struct Report<'a> {
+                stats: &'a Stats,
+                metrics: &'a Metrics,
+                types: &'a TypeHistogram,
+            }
+            let report = Report { stats: &stats, metrics: &metrics, types: &types };
+            format!("{}\n", serde_json::to_string_pretty(&report).unwrap())
+        }
+        _ => format!("{}{}{}", stats, metrics, types),
     }
 }
@@ -1,13 +1,5 @@
-pub mod lir;
-pub mod stats;
-
 use std::collections::HashMap;
-use std::fs;
-use std::io::{self, Result};
-use std::path::{Path, PathBuf};
-use std::process::exit;
-use lir::Program;
-use stats::*;
+use std::io::Result;
 
 fn main() -> Result<()> {
 
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::Serialize;
+use crate::lir::{*};
+
+// classification of every type written in a program by its shape: the type at the bottom of its
+// pointers and how many pointers lead to it. Stats derives its legacy type counts from this.
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Base {
+    Int,
+    Struct(String),
+    Function,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Shape {
+    // number of pointers above the base, 0 for the base itself
+    pub depth: usize,
+    pub base: Base,
+}
+
+impl Shape {
+    pub fn of(typ: &Type) -> Shape {
+        match typ {
+            Type::Pointer(t) => {
+                let inner = Shape::of(t);
+                Shape { depth: inner.depth + 1, ..inner }
+            }
+            Type::Int => Shape { depth: 0, base: Base::Int },
+            Type::Struct(name) => Shape { depth: 0, base: Base::Struct(name.clone()) },
+            Type::Function(_) => Shape { depth: 0, base: Base::Function },
+        }
+    }
+
+    // the histogram key: the shape without struct names, e.g. "&&int", "&struct" or "fn"
+    pub fn class(&self) -> String {
+        let base = match self.base {
+            Base::Int => "int",
+            Base::Struct(_) => "struct",
+            Base::Function => "fn",
+        };
+        format!("{}{}", "&".repeat(self.depth), base)
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.base {
+            Base::Struct(name) => write!(f, "{}{}", "&".repeat(self.depth), name),
+            _ => write!(f, "{}", self.class()),
+        }
+    }
+}
+
+// where a type is written
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+    Global,
+    Local,
+    Param,
+    Return,
+    Field,
+    ExternParam,
+    ExternReturn,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Origin::Global => "globals",
+            Origin::Local => "locals",
+            Origin::Param => "parameters",
+            Origin::Return => "return types",
+            Origin::Field => "struct fields",
+            Origin::ExternParam => "extern parameters",
+            Origin::ExternReturn => "extern return types",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// every type occurrence of a program with its shape
+#[derive(Debug, Default)]
+pub struct TypeCensus {
+    pub occurrences: Vec<(Origin, Shape)>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Default)]
+pub struct TypeHistogram {
+    pub max_pointer_depth: usize,
+    // shape class -> occurrences anywhere in the program
+    pub shapes: BTreeMap<String, u32>,
+    pub by_origin: BTreeMap<Origin, BTreeMap<String, u32>>,
+}
+
+impl TypeCensus {
+    pub fn new(program: &Program) -> TypeCensus {
+        let mut census = TypeCensus::default();
+        program.globals.iter().for_each(|g| census.add(Origin::Global, &g.typ));
+        program.structs.values().flatten().for_each(|f| census.add(Origin::Field, &f.typ));
+        for function in program.functions.values() {
+            function.locals.iter().for_each(|v| census.add(Origin::Local, &v.typ));
+            function.params.iter().for_each(|v| census.add(Origin::Param, &v.typ));
+            if let Some(t) = &function.ret_ty { census.add(Origin::Return, t); }
+        }
+        for typ in program.externs.values() {
+            if let Type::Function(ft) = typ {
+                ft.param_ty.iter().for_each(|t| census.add(Origin::ExternParam, t));
+                if let Some(t) = &ft.ret_ty { census.add(Origin::ExternReturn, t); }
+            }
+        }
+        census
+    }
+
+    fn add(&mut self, origin: Origin, typ: &Type) {
+        self.occurrences.push((origin, Shape::of(typ)));
+    }
+
+    // occurrences from any of origins whose shape satisfies pred
+    pub fn count(&self, origins: &[Origin], pred: impl Fn(&Shape) -> bool) -> u32 {
+        self.occurrences.iter().filter(|(origin, shape)| origins.contains(origin) && pred(shape)).count() as u32
+    }
+
+    pub fn histogram(&self) -> TypeHistogram {
+        let mut histogram = TypeHistogram::default();
+        for (origin, shape) in self.occurrences.iter() {
+            histogram.max_pointer_depth = histogram.max_pointer_depth.max(shape.depth);
+            *histogram.shapes.entry(shape.class()).or_default() += 1;
+            *histogram.by_origin.entry(*origin).or_default().entry(shape.class()).or_default() += 1;
+        }
+        histogram
+    }
+}
+
+impl fmt::Display for TypeHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Types by shape:")?;
+        for (class, n) in self.shapes.iter() {
+            writeln!(f, "  {}: {}", class, n)?;
+        }
+        for (origin, shapes) in self.by_origin.iter() {
+            let counts: Vec<String> = shapes.iter().map(|(class, n)| format!("{} {}", class, n)).collect();
+            writeln!(f, "Types of {}: {}", origin, counts.join(", "))?;
+        }
+        writeln!(f, "Maximum pointer depth: {}", self.max_pointer_depth)
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::Serialize;
+use crate::lir::{*};
+
+// a well-formedness problem, located as "function", "function.block" or "function.block.index"
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub location: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+// check that a program is well formed: every block target, callee, struct and variable exists,
+// variables are used with their declared types, and every instruction and terminal is well typed.
+// all problems are reported, sorted by location.
+pub fn validate(program: &Program) -> Result<(), Vec<ValidationError>> {
+    let mut checker = Checker { program, errors: vec![] };
+    checker.check_program();
+    if checker.errors.is_empty() {
+        Ok(())
+    } else {
+        checker.errors.sort_by(|a, b| a.location.cmp(&b.location));
+        Err(checker.errors)
+    }
+}
+
+struct Checker<'a> {
+    program: &'a Program,
+    errors: Vec<ValidationError>,
+}
+
+// the variables visible in a function: its params and locals, then the globals
+struct Scope<'a> {
+    vars: HashMap<&'a str, &'a Type>,
+}
+
+fn pointee(typ: &Type) -> Option<&Type> {
+    match typ {
+        Type::Pointer(t) => Some(t),
+        _ => None,
+    }
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, location: &str, message: String) {
+        self.errors.push(ValidationError { location: location.to_string(), message });
+    }
+
+    fn check_type(&mut self, location: &str, typ: &Type) {
+        match typ {
+            Type::Int => {}
+            Type::Struct(name) => {
+                if !self.program.structs.contains_key(name) {
+                    self.error(location, format!("unknown struct type {}", name));
+                }
+            }
+            Type::Pointer(t) => self.check_type(location, t),
+            Type::Function(ft) => {
+                ft.param_ty.iter().for_each(|t| self.check_type(location, t));
+                if let Some(t) = &ft.ret_ty { self.check_type(location, t); }
+            }
+        }
+    }
+
+    fn check_program(&mut self) {
+        let program = self.program;
+        let mut structs: Vec<&String> = program.structs.keys().collect();
+        structs.sort();
+        for name in structs {
+            for field in program.structs[name].iter() {
+                self.check_type(&format!("struct {}", name), &field.typ);
+            }
+        }
+        let mut names: HashMap<&str, usize> = HashMap::new();
+        for global in program.globals.iter() {
+            self.check_type("globals", &global.typ);
+            *names.entry(&global.name).or_default() += 1;
+        }
+        let mut duplicates: Vec<&&str> = names.iter().filter(|(_, n)| **n > 1).map(|(name, _)| name).collect();
+        duplicates.sort();
+        for name in duplicates {
+            self.error("globals", format!("global {} is declared more than once", name));
+        }
+        let mut externs: Vec<&String> = program.externs.keys().collect();
+        externs.sort();
+        for name in externs {
+            match &program.externs[name] {
+                Type::Function(_) => self.check_type(&format!("extern {}", name), &program.externs[name]),
+                typ => self.error(&format!("extern {}", name), format!("extern has non-function type {:?}", typ)),
+            }
+        }
+        let mut functions: Vec<&String> = program.functions.keys().collect();
+        functions.sort();
+        for name in functions {
+            self.check_function(name, &program.functions[name]);
+        }
+    }
+
+    fn check_function(&mut self, name: &str, function: &'a Function) {
+        if function.id != name {
+            self.error(name, format!("function is stored under {} but has id {}", name, function.id));
+        }
+        let mut scope = Scope { vars: HashMap::new() };
+        self.program.globals.iter().for_each(|g| { scope.vars.insert(&g.name, &g.typ); });
+        let mut seen: Vec<&str> = vec![];
+        for var in function.params.iter().chain(function.locals.iter()) {
+            self.check_type(name, &var.typ);
+            if seen.contains(&var.name.as_str()) {
+                self.error(name, format!("variable {} is declared more than once", var.name));
+            }
+            seen.push(&var.name);
+            scope.vars.insert(&var.name, &var.typ);
+        }
+        if let Some(t) = &function.ret_ty { self.check_type(name, t); }
+        if !function.body.contains_key("entry") {
+            self.error(name, "function has no entry block".to_string());
+        }
+        let mut blocks: Vec<&String> = function.body.keys().collect();
+        blocks.sort();
+        for bb_name in blocks {
+            let bb = &function.body[bb_name];
+            if &bb.id != bb_name {
+                self.error(&format!("{}.{}", name, bb_name), format!("block is stored under {} but has id {}", bb_name, bb.id));
+            }
+            for (i, inst) in bb.insts.iter().enumerate() {
+                self.check_inst(&format!("{}.{}.{}", name, bb_name, i), inst, &scope);
+            }
+            self.check_term(&format!("{}.{}.{}", name, bb_name, bb.insts.len()), &bb.term, function, &scope);
+        }
+    }
+
+    // a variable must be in scope with the type it is declared with
+    fn check_var(&mut self, location: &str, var: &Variable, scope: &Scope) {
+        match scope.vars.get(var.name.as_str()) {
+            None => self.error(location, format!("variable {} is not declared", var.name)),
+            Some(typ) if **typ != var.typ =>
+                self.error(location, format!("variable {} is used as {} but declared as {}", var.name, var.typ, typ)),
+            _ => {}
+        }
+    }
+
+    // check an operand and return its type
+    fn check_operand(&mut self, location: &str, op: &Operand, scope: &Scope) -> Type {
+        match op {
+            Operand::Var(v) => {
+                self.check_var(location, v, scope);
+                v.typ.clone()
+            }
+            Operand::CInt(_) => Type::Int,
+        }
+    }
+
+    fn expect(&mut self, location: &str, what: &str, expected: &Type, found: &Type) {
+        if expected != found {
+            self.error(location, format!("{} should have type {} but has type {}", what, expected, found));
+        }
+    }
+
+    fn check_args(&mut self, location: &str, callee: &str, ft: &FunctionType, lhs: &Option<Variable>, args: &[Operand], scope: &Scope) {
+        if ft.param_ty.len() != args.len() {
+            self.error(location, format!("{} takes {} arguments but {} are given", callee, ft.param_ty.len(), args.len()));
+        }
+        for (i, (arg, typ)) in args.iter().zip(ft.param_ty.iter()).enumerate() {
+            let found = self.check_operand(location, arg, scope);
+            self.expect(location, &format!("argument {} of {}", i, callee), typ, &found);
+        }
+        match (lhs, &ft.ret_ty) {
+            (Some(lhs), Some(ret_ty)) => {
+                self.check_var(location, lhs, scope);
+                self.expect(location, &format!("result of {}", callee), &lhs.typ, ret_ty);
+            }
+            (Some(_), None) => self.error(location, format!("{} returns no value to assign", callee)),
+            _ => {}
+        }
+    }
+
+    fn check_inst(&mut self, location: &str, inst: &Instruction, scope: &Scope) {
+        match inst {
+            Instruction::AddrOf { lhs, rhs } => {
+                self.check_var(location, lhs, scope);
+                self.check_var(location, rhs, scope);
+                self.expect(location, "lhs of $addrof", &Type::Pointer(Box::new(rhs.typ.clone())), &lhs.typ);
+            }
+            Instruction::Alloc { lhs, num, .. } => {
+                self.check_var(location, lhs, scope);
+                let num = self.check_operand(location, num, scope);
+                self.expect(location, "size of $alloc", &Type::Int, &num);
+                if pointee(&lhs.typ).is_none() {
+                    self.error(location, format!("lhs of $alloc should be a pointer but has type {}", lhs.typ));
+                }
+            }
+            Instruction::Copy { lhs, op } => {
+                self.check_var(location, lhs, scope);
+                let op = self.check_operand(location, op, scope);
+                self.expect(location, "operand of $copy", &lhs.typ, &op);
+            }
+            Instruction::Gep { lhs, src, idx } => {
+                self.check_var(location, lhs, scope);
+                self.check_var(location, src, scope);
+                let idx = self.check_operand(location, idx, scope);
+                self.expect(location, "index of $gep", &Type::Int, &idx);
+                if pointee(&src.typ).is_none() {
+                    self.error(location, format!("source of $gep should be a pointer but has type {}", src.typ));
+                }
+                self.expect(location, "lhs of $gep", &src.typ, &lhs.typ);
+            }
+            Instruction::Arith { lhs, op1, op2, .. } => {
+                self.check_var(location, lhs, scope);
+                self.expect(location, "lhs of $arith", &Type::Int, &lhs.typ);
+                let op1 = self.check_operand(location, op1, scope);
+                self.expect(location, "operand of $arith", &Type::Int, &op1);
+                let op2 = self.check_operand(location, op2, scope);
+                self.expect(location, "operand of $arith", &Type::Int, &op2);
+            }
+            Instruction::Load { lhs, src } => {
+                self.check_var(location, lhs, scope);
+                self.check_var(location, src, scope);
+                match pointee(&src.typ) {
+                    Some(t) => self.expect(location, "lhs of $load", t, &lhs.typ),
+                    None => self.error(location, format!("source of $load should be a pointer but has type {}", src.typ)),
+                }
+            }
+            Instruction::Store { dst, op } => {
+                self.check_var(location, dst, scope);
+                let op = self.check_operand(location, op, scope);
+                match pointee(&dst.typ) {
+                    Some(t) => self.expect(location, "operand of $store", t, &op),
+                    None => self.error(location, format!("destination of $store should be a pointer but has type {}", dst.typ)),
+                }
+            }
+            Instruction::Gfp { lhs, src, field } => {
+                self.check_var(location, lhs, scope);
+                self.check_var(location, src, scope);
+                let fields = match pointee(&src.typ) {
+                    Some(Type::Struct(name)) => self.program.structs.get(name),
+                    _ => {
+                        self.error(location, format!("source of $gfp should point to a struct but has type {}", src.typ));
+                        return;
+                    }
+                };
+                match fields.and_then(|fields| fields.iter().find(|f| f.name == field.name)) {
+                    Some(f) => self.expect(location, "lhs of $gfp", &Type::Pointer(Box::new(f.typ.clone())), &lhs.typ),
+                    None => self.error(location, format!("{} has no field {}", src.typ, field.name)),
+                }
+            }
+            Instruction::Cmp { lhs, op1, op2, .. } => {
+                self.check_var(location, lhs, scope);
+                self.expect(location, "lhs of $cmp", &Type::Int, &lhs.typ);
+                let op1 = self.check_operand(location, op1, scope);
+                let op2 = self.check_operand(location, op2, scope);
+                self.expect(location, "second operand of $cmp", &op1, &op2);
+            }
+            Instruction::CallExt { lhs, ext_callee, args } => {
+                match self.program.externs.get(ext_callee) {
+                    Some(Type::Function(ft)) => self.check_args(location, ext_callee, ft, lhs, args, scope),
+                    _ => self.error(location, format!("unknown extern function {}", ext_callee)),
+                }
+            }
+        }
+    }
+
+    fn check_term(&mut self, location: &str, term: &Terminal, function: &Function, scope: &Scope) {
+        let targets: Vec<&String> = match term {
+            Terminal::Jump(target) => vec![target],
+            Terminal::Branch { tt, ff, .. } => vec![tt, ff],
+            Terminal::CallDirect { next_bb, .. } |
+            Terminal::CallIndirect { next_bb, .. } => vec![next_bb],
+            Terminal::Ret(_) => vec![],
+        };
+        for target in targets {
+            if !function.body.contains_key(target) {
+                self.error(location, format!("jump to unknown block {}", target));
+            }
+        }
+        match term {
+            Terminal::Jump(_) => {}
+            Terminal::Branch { cond, .. } => {
+                let cond = self.check_operand(location, cond, scope);
+                self.expect(location, "branch condition", &Type::Int, &cond);
+            }
+            Terminal::Ret(op) => match (op, &function.ret_ty) {
+                (Some(op), Some(ret_ty)) => {
+                    let op = self.check_operand(location, op, scope);
+                    self.expect(location, "returned value", ret_ty, &op);
+                }
+                (Some(_), None) => self.error(location, format!("{} returns a value but has no return type", function.id)),
+                (None, Some(_)) => self.error(location, format!("{} must return a value", function.id)),
+                (None, None) => {}
+            },
+            Terminal::CallDirect { lhs, callee, args, .. } => {
+                match self.program.functions.get(callee) {
+                    Some(f) => {
+                        let ft = FunctionType {
+                            ret_ty: f.ret_ty.clone(),
+                            param_ty: f.params.iter().map(|p| p.typ.clone()).collect(),
+                        };
+                        self.check_args(location, callee, &ft, lhs, args, scope);
+                    }
+                    None => self.error(location, format!("unknown function {}", callee)),
+                }
+            }
+            Terminal::CallIndirect { lhs, callee, args, .. } => {
+                self.check_var(location, callee, scope);
+                match pointee(&callee.typ) {
+                    Some(Type::Function(ft)) => self.check_args(location, &callee.name, ft, lhs, args, scope),
+                    _ => self.error(location, format!("callee {} should be a function pointer but has type {}", callee.name, callee.typ)),
+                }
+            }
+        }
+    }
+}
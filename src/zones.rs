@@ -0,0 +1,536 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::cfg::Cfg;
+use crate::domain::Globals;
+use crate::dot;
+use crate::lir::{*};
+use crate::report::{Fact, OutputFormat, Report};
+
+// the zone analysis: a relational domain of difference bounds x - y <= c between the ints of a
+// function, kept as a difference-bound matrix (dbm) in which index 0 stands for the constant 0, so
+// that x <= c is x - 0 <= c. unlike intervals it can prove i < n inside a loop guarded by it.
+// copies and additions of constants are tracked exactly when they cannot wrap around, other
+// arithmetic through the bounds of its operands, and every $branch on a $cmp of the same block
+// refines the zone along each of its edges. loops are made to converge by widening, which drops
+// the bounds that keep growing at the targets of retreating edges, except those of the int type.
+
+// no bound
+const INF: i64 = i64::MAX;
+const MIN: i64 = i32::MIN as i64;
+const MAX: i64 = i32::MAX as i64;
+
+fn add(a: i64, b: i64) -> i64 {
+    if a == INF || b == INF { INF } else { a + b }
+}
+
+// the ints a zone relates, in a fixed order: the int globals, then the int params and locals
+#[derive(Debug, Clone)]
+pub struct Vars {
+    pub names: Vec<String>,
+    index: HashMap<String, usize>,
+}
+
+impl Vars {
+    pub fn new(program: &Program, function: &Function) -> Vars {
+        let mut names: Vec<String> = vec![];
+        for v in program.globals.iter().chain(function.params.iter()).chain(function.locals.iter()) {
+            if v.typ == Type::Int && !names.contains(&v.name) {
+                names.push(v.name.clone());
+            }
+        }
+        let index = names.iter().enumerate().map(|(i, name)| (name.clone(), i + 1)).collect();
+        Vars { names, index }
+    }
+
+    // the row of var in the matrix, if it is a tracked int
+    pub fn index(&self, var: &str) -> Option<usize> {
+        self.index.get(var).copied()
+    }
+}
+
+// a non-empty set of states, as a dbm: m[i * n + j] bounds v_i - v_j
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Zone {
+    n: usize,
+    m: Vec<i64>,
+}
+
+impl Zone {
+    // every variable anywhere in the range of int
+    pub fn top(vars: &Vars) -> Zone {
+        let n = vars.names.len() + 1;
+        let mut zone = Zone { n, m: vec![INF; n * n] };
+        for i in 0..n {
+            zone.set(i, i, 0);
+        }
+        (1..n).for_each(|i| zone.clamp(i));
+        zone
+    }
+
+    fn get(&self, i: usize, j: usize) -> i64 {
+        self.m[i * self.n + j]
+    }
+
+    fn set(&mut self, i: usize, j: usize, bound: i64) {
+        self.m[i * self.n + j] = bound;
+    }
+
+    // v_i - v_j <= bound, keeping the tighter of the two bounds
+    fn tighten(&mut self, i: usize, j: usize, bound: i64) {
+        if bound < self.get(i, j) {
+            self.set(i, j, bound);
+        }
+    }
+
+    // the range of int, which every variable is in whatever else is known
+    fn clamp(&mut self, i: usize) {
+        self.tighten(i, 0, MAX);
+        self.tighten(0, i, -MIN);
+    }
+
+    // the tightest bounds, by floyd-warshall; None if the constraints contradict each other
+    pub fn close(mut self) -> Option<Zone> {
+        let n = self.n;
+        for k in 0..n {
+            for i in 0..n {
+                let ik = self.get(i, k);
+                if ik == INF { continue; }
+                for j in 0..n {
+                    let through = add(ik, self.get(k, j));
+                    if through < self.get(i, j) {
+                        self.set(i, j, through);
+                    }
+                }
+            }
+        }
+        if (0..n).any(|i| self.get(i, i) < 0) {
+            return None;
+        }
+        Some(self)
+    }
+
+    // the smallest zone containing both; both must be closed for the result to be the best one
+    pub fn join(&self, other: &Zone) -> Zone {
+        let m = self.m.iter().zip(other.m.iter()).map(|(a, b)| *a.max(b)).collect();
+        Zone { n: self.n, m }
+    }
+
+    // self joined with next, dropping every bound of self that next does not keep
+    pub fn widen(&self, next: &Zone) -> Zone {
+        let m = self.m.iter().zip(next.m.iter()).map(|(a, b)| if b > a { INF } else { *a }).collect();
+        let mut zone = Zone { n: self.n, m };
+        (1..self.n).for_each(|i| zone.clamp(i));
+        zone
+    }
+
+    // the lower and upper bound of v_i, in a closed zone
+    pub fn bounds(&self, i: usize) -> (i64, i64) {
+        (-self.get(0, i), self.get(i, 0))
+    }
+
+    // the bound of v_i - v_j, None if there is none, in a closed zone
+    pub fn difference(&self, i: usize, j: usize) -> Option<i64> {
+        Some(self.get(i, j)).filter(|bound| *bound != INF)
+    }
+
+    // v_i may hold any int, unrelated to the other variables
+    fn forget(&mut self, i: usize) {
+        for j in 0..self.n {
+            if j != i {
+                self.set(i, j, INF);
+                self.set(j, i, INF);
+            }
+        }
+        self.clamp(i);
+    }
+
+    fn assign_range(&mut self, i: usize, (lo, hi): (i64, i64)) {
+        self.forget(i);
+        self.tighten(i, 0, hi);
+        self.tighten(0, i, -lo);
+    }
+
+    // v_i = v_j + c, where the sum cannot wrap around
+    fn assign_offset(&mut self, i: usize, j: usize, c: i64) {
+        if i == j {
+            for k in 0..self.n {
+                if k != i {
+                    self.set(i, k, add(self.get(i, k), c));
+                    self.set(k, i, add(self.get(k, i), -c));
+                }
+            }
+        } else {
+            self.forget(i);
+            self.tighten(i, j, c);
+            self.tighten(j, i, -c);
+        }
+    }
+}
+
+// an operand as v_i + c, with index 0 for a constant
+fn linear(vars: &Vars, op: &Operand) -> Option<(usize, i64)> {
+    match op {
+        Operand::CInt(c) => Some((0, *c as i64)),
+        Operand::Var(v) => vars.index(&v.name).map(|i| (i, 0)),
+    }
+}
+
+fn range(zone: &Zone, vars: &Vars, op: &Operand) -> (i64, i64) {
+    match linear(vars, op) {
+        Some((0, c)) => (c, c),
+        Some((i, _)) => zone.bounds(i),
+        None => (MIN, MAX),
+    }
+}
+
+// a range of i64 results, or the whole int range if some of them wrap around
+fn fits((lo, hi): (i64, i64)) -> (i64, i64) {
+    if lo < MIN || hi > MAX { (MIN, MAX) } else { (lo, hi) }
+}
+
+// the facts a $cmp of the block established, for refining the branches on its result
+type Conditions = HashMap<String, (RelaOp, Operand, Operand)>;
+
+// the zone with a - b <= c added, None if that leaves nothing
+fn constrain(zone: &Zone, vars: &Vars, a: &Operand, b: &Operand, c: i64) -> Option<Zone> {
+    let ((i, ca), (j, cb)) = match (linear(vars, a), linear(vars, b)) {
+        (Some(a), Some(b)) => (a, b),
+        // an operand zones do not track, such as a pointer, constrains nothing
+        _ => return Some(zone.clone()),
+    };
+    // v_i + ca - (v_j + cb) <= c
+    let bound = c - ca + cb;
+    if i == j {
+        return if bound >= 0 { Some(zone.clone()) } else { None };
+    }
+    let mut zone = zone.clone();
+    zone.tighten(i, j, bound);
+    zone.close()
+}
+
+// the zone in which op1 rop op2 holds, None if it cannot. a relation zones cannot express, such as
+// op1 != op2, leaves the zone as it is
+fn refine(zone: &Zone, vars: &Vars, rop: &RelaOp, op1: &Operand, op2: &Operand) -> Option<Zone> {
+    match rop {
+        RelaOp::Less => constrain(zone, vars, op1, op2, -1),
+        RelaOp::LessEq => constrain(zone, vars, op1, op2, 0),
+        RelaOp::Greater => constrain(zone, vars, op2, op1, -1),
+        RelaOp::GreaterEq => constrain(zone, vars, op2, op1, 0),
+        RelaOp::Eq => constrain(&constrain(zone, vars, op1, op2, 0)?, vars, op2, op1, 0),
+        RelaOp::Neq => match (range(zone, vars, op1), range(zone, vars, op2)) {
+            ((a, b), (c, d)) if a == b && c == d && a == c => None,
+            _ => Some(zone.clone()),
+        },
+    }
+}
+
+fn negate(rop: &RelaOp) -> RelaOp {
+    match rop {
+        RelaOp::Eq => RelaOp::Neq,
+        RelaOp::Neq => RelaOp::Eq,
+        RelaOp::Less => RelaOp::GreaterEq,
+        RelaOp::LessEq => RelaOp::Greater,
+        RelaOp::Greater => RelaOp::LessEq,
+        RelaOp::GreaterEq => RelaOp::Less,
+    }
+}
+
+fn mentions(op: &Operand, var: &str) -> bool {
+    matches!(op, Operand::Var(v) if v.name == var)
+}
+
+struct Analysis<'a> {
+    vars: Vars,
+    globals: Globals,
+    function: &'a Function,
+}
+
+// forget the facts of conditions that var no longer makes true
+fn invalidate(conditions: &mut Conditions, var: &str) {
+    conditions.retain(|lhs, (_, op1, op2)| lhs != var && !mentions(op1, var) && !mentions(op2, var));
+}
+
+impl Analysis<'_> {
+    fn assigned(&self, zone: &mut Zone, conditions: &mut Conditions, var: &str) {
+        invalidate(conditions, var);
+        if let Some(i) = self.vars.index(var) {
+            zone.forget(i);
+        }
+    }
+
+    fn call(&self, zone: &mut Zone, conditions: &mut Conditions, lhs: &Option<Variable>, args: &[Operand]) {
+        for v in self.globals.global_ints.iter() {
+            self.assigned(zone, conditions, &v.name);
+        }
+        if let Some(lhs) = lhs {
+            self.assigned(zone, conditions, &lhs.name);
+        }
+        let any_arg_reaches_int = args.iter().any(|arg| matches!(arg, Operand::Var(v) if v.typ.is_pointer_to_int()));
+        if any_arg_reaches_int || self.globals.global_ptr_to_ints {
+            for v in self.globals.addr_taken_ints.iter() {
+                self.assigned(zone, conditions, &v.name);
+            }
+        }
+    }
+
+    // the zone after inst, None if inst cannot complete
+    fn execute(&self, zone: Zone, conditions: &mut Conditions, inst: &Instruction) -> Option<Zone> {
+        let mut next = zone.clone();
+        match inst {
+            Instruction::Copy { lhs, op } => {
+                invalidate(conditions, &lhs.name);
+                if let Some(i) = self.vars.index(&lhs.name) {
+                    match linear(&self.vars, op) {
+                        Some((0, c)) => next.assign_range(i, (c, c)),
+                        Some((j, _)) => next.assign_offset(i, j, 0),
+                        None => next.forget(i),
+                    }
+                }
+            }
+            Instruction::Arith { lhs, aop, op1, op2 } => {
+                let (a, b) = (range(&zone, &self.vars, op1), range(&zone, &self.vars, op2));
+                // a division by zero faults
+                if *aop == ArithOp::Divide && b == (0, 0) {
+                    return None;
+                }
+                let result = match aop {
+                    ArithOp::Add => fits((a.0 + b.0, a.1 + b.1)),
+                    ArithOp::Subtract => fits((a.0 - b.1, a.1 - b.0)),
+                    ArithOp::Multiply => {
+                        let products = [a.0 * b.0, a.0 * b.1, a.1 * b.0, a.1 * b.1];
+                        fits((*products.iter().min().unwrap(), *products.iter().max().unwrap()))
+                    }
+                    ArithOp::Divide => (MIN, MAX),
+                };
+                // v_j + c, c + v_j and v_j - c are exact when no result wraps around
+                let offset = match (aop, linear(&self.vars, op1), linear(&self.vars, op2)) {
+                    _ if result == (MIN, MAX) => None,
+                    (ArithOp::Add, Some((j, 0)), Some((0, c))) |
+                    (ArithOp::Add, Some((0, c)), Some((j, 0))) if j != 0 => Some((j, c)),
+                    (ArithOp::Subtract, Some((j, 0)), Some((0, c))) if j != 0 => Some((j, -c)),
+                    _ => None,
+                };
+                invalidate(conditions, &lhs.name);
+                if let Some(i) = self.vars.index(&lhs.name) {
+                    match offset {
+                        Some((j, c)) => next.assign_offset(i, j, c),
+                        None => next.assign_range(i, result),
+                    }
+                }
+            }
+            Instruction::Cmp { lhs, rop, op1, op2 } => {
+                let may_hold = refine(&zone, &self.vars, rop, op1, op2).is_some();
+                let may_fail = refine(&zone, &self.vars, &negate(rop), op1, op2).is_some();
+                self.assigned(&mut next, conditions, &lhs.name);
+                if let Some(i) = self.vars.index(&lhs.name) {
+                    next.assign_range(i, (if may_fail { 0 } else { 1 }, if may_hold { 1 } else { 0 }));
+                }
+                let operands_kept = !mentions(op1, &lhs.name) && !mentions(op2, &lhs.name);
+                if operands_kept {
+                    conditions.insert(lhs.name.clone(), (rop.clone(), op1.clone(), op2.clone()));
+                }
+            }
+            Instruction::Load { lhs, .. } |
+            Instruction::Gep { lhs, .. } |
+            Instruction::Gfp { lhs, .. } |
+            Instruction::AddrOf { lhs, .. } |
+            Instruction::Alloc { lhs, .. } => self.assigned(&mut next, conditions, &lhs.name),
+            Instruction::Store { dst, .. } => {
+                // a store through a pointer to int may write any address-taken int
+                if dst.typ.is_pointer_to_int() {
+                    for v in self.globals.addr_taken_ints.iter() {
+                        self.assigned(&mut next, conditions, &v.name);
+                    }
+                }
+            }
+            Instruction::CallExt { lhs, args, .. } => self.call(&mut next, conditions, lhs, args),
+        }
+        next.close()
+    }
+
+    // the zone at the end of bb and along each edge leaving it, starting from entry
+    fn transfer(&self, bb: &Block, entry: &Zone) -> (Option<Zone>, Vec<(String, Zone)>) {
+        let mut conditions = Conditions::new();
+        let mut zone = entry.clone();
+        for inst in bb.insts.iter() {
+            zone = match self.execute(zone, &mut conditions, inst) {
+                Some(zone) => zone,
+                None => return (None, vec![]),
+            };
+        }
+        let edges = match &bb.term {
+            Terminal::Jump(target) => vec![(target.clone(), Some(zone.clone()))],
+            Terminal::Branch { cond, tt, ff } => {
+                let zero = Operand::CInt(0);
+                // the true edge has a non-zero condition: a positive one if it cannot be
+                // negative, a negative one if it cannot be positive
+                let mut on_true = match range(&zone, &self.vars, cond) {
+                    (lo, _) if lo >= 0 => constrain(&zone, &self.vars, &zero, cond, -1),
+                    (_, hi) if hi <= 0 => constrain(&zone, &self.vars, cond, &zero, -1),
+                    _ => Some(zone.clone()),
+                };
+                let mut on_false = refine(&zone, &self.vars, &RelaOp::Eq, cond, &zero);
+                if let Operand::Var(v) = cond {
+                    if let Some((rop, op1, op2)) = conditions.get(&v.name) {
+                        on_true = on_true.and_then(|z| refine(&z, &self.vars, rop, op1, op2));
+                        on_false = on_false.and_then(|z| refine(&z, &self.vars, &negate(rop), op1, op2));
+                    }
+                }
+                vec![(tt.clone(), on_true), (ff.clone(), on_false)]
+            }
+            Terminal::CallDirect { lhs, args, next_bb, .. } |
+            Terminal::CallIndirect { lhs, args, next_bb, .. } => {
+                // the end of a block that calls is after the call returns, as in the other analyses
+                self.call(&mut zone, &mut conditions, lhs, args);
+                vec![(next_bb.clone(), Some(zone.clone()))]
+            }
+            Terminal::Ret(_) => vec![],
+        };
+        let edges = edges.into_iter().filter_map(|(target, zone)| zone.map(|zone| (target, zone))).collect();
+        (Some(zone), edges)
+    }
+}
+
+// the zones of one function: at the start and at the end of every block the worklist reached
+#[derive(Debug, Clone)]
+pub struct Zones {
+    pub vars: Vars,
+    pub entry: HashMap<String, Zone>,
+    pub exit: HashMap<String, Zone>,
+    // blocks processed by the worklist
+    pub iterations: usize,
+}
+
+impl Zones {
+    pub fn new(program: &Program, function_name: &str) -> Zones {
+        let function = &program.functions[function_name];
+        let analysis = Analysis {
+            vars: Vars::new(program, function),
+            globals: Globals::new(program, function_name),
+            function,
+        };
+        let widen_at = Cfg::new(function).widening_points();
+
+        let mut entry: HashMap<String, Zone> = HashMap::new();
+        let mut exit: HashMap<String, Zone> = HashMap::new();
+        let mut iterations = 0;
+        let mut working_list = VecDeque::from(["entry".to_string()]);
+        entry.insert("entry".to_string(), Zone::top(&analysis.vars));
+        while let Some(bb_name) = working_list.pop_front() {
+            iterations += 1;
+            let bb = &analysis.function.body[&bb_name];
+            let (end, edges) = analysis.transfer(bb, &entry[&bb_name]);
+            match end {
+                Some(end) => exit.insert(bb_name.clone(), end),
+                None => exit.remove(&bb_name),
+            };
+            for (target, zone) in edges {
+                let new = match entry.get(&target) {
+                    None => zone,
+                    Some(old) if widen_at.contains(&target) => old.widen(&old.join(&zone)),
+                    Some(old) => old.join(&zone),
+                };
+                if entry.get(&target) != Some(&new) {
+                    entry.insert(target.clone(), new);
+                    if !working_list.contains(&target) {
+                        working_list.push_back(target);
+                    }
+                }
+            }
+        }
+        Zones { vars: analysis.vars, entry, exit, iterations }
+    }
+
+    // the zone just before every instruction of block, and last the one before its terminal, up to
+    // the first instruction no execution gets past; None if the worklist never reached the block
+    pub fn points(&self, program: &Program, function_name: &str, block: &str) -> Option<Vec<Zone>> {
+        let function = &program.functions[function_name];
+        let analysis = Analysis {
+            vars: self.vars.clone(),
+            globals: Globals::new(program, function_name),
+            function,
+        };
+        let mut conditions = Conditions::new();
+        let mut zone = self.entry.get(block)?.clone().close()?;
+        let mut zones = vec![zone.clone()];
+        for inst in function.body[block].insts.iter() {
+            zone = match analysis.execute(zone, &mut conditions, inst) {
+                Some(zone) => zone,
+                None => break,
+            };
+            zones.push(zone.clone());
+        }
+        Some(zones)
+    }
+
+    // the zone just before instruction index of block, e.g. to check the index of a $gep against
+    // the length it was allocated with; None if the worklist never reached it
+    pub fn before(&self, program: &Program, function_name: &str, block: &str, index: usize) -> Option<Zone> {
+        self.points(program, function_name, block)?.into_iter().nth(index)
+    }
+
+    // the facts of a closed zone as "x -> [lo, hi]" for the bounded variables, then "x - y <= c"
+    // for the differences tighter than the bounds of x and y imply
+    pub fn facts(&self, zone: &Zone) -> BTreeMap<String, String> {
+        let mut facts = BTreeMap::new();
+        let zone = zone.clone().close().expect("zones are not empty");
+        let mut names: Vec<(&String, usize)> = self.vars.names.iter().enumerate().map(|(i, name)| (name, i + 1)).collect();
+        names.sort();
+        for (name, i) in names.iter() {
+            let (lo, hi) = zone.bounds(*i);
+            if (lo, hi) != (MIN, MAX) {
+                facts.insert(name.to_string(), if lo == hi { lo.to_string() } else { format!("[{}, {}]", lo, hi) });
+            }
+        }
+        for (x, i) in names.iter() {
+            for (y, j) in names.iter() {
+                let implied = zone.bounds(*i).1 - zone.bounds(*j).0;
+                match zone.difference(*i, *j) {
+                    Some(bound) if i != j && bound < implied => {
+                        facts.insert(format!("{} - {}", x, y), format!("<= {}", bound));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        facts
+    }
+}
+
+fn lines(zones: &Zones) -> HashMap<String, Vec<String>> {
+    zones.exit.iter().map(|(block, zone)| {
+        let lines = zones.facts(zone).into_iter()
+            .map(|(key, fact)| if fact.starts_with("<=") { format!("{} {}", key, fact) } else { format!("{} -> {}", key, fact) })
+            .collect();
+        (block.clone(), lines)
+    }).collect()
+}
+
+fn report(function_name: &str, zones: &Zones) -> Report {
+    let mut report = Report::new("zones", function_name, Some(zones.iterations));
+    report.blocks = zones.exit.iter().map(|(block, zone)| {
+        let facts = zones.facts(zone).into_iter().map(|(key, fact)| (key, Fact::Value(fact))).collect();
+        (block.clone(), facts)
+    }).collect();
+    report
+}
+
+// the zone at the end of every block, in the format of the other value analyses
+pub fn render(program: &Program, function_name: &str, format: OutputFormat) -> String {
+    let zones = Zones::new(program, function_name);
+    match format {
+        OutputFormat::Text => {
+            let lines = lines(&zones);
+            let mut blocks: Vec<&String> = lines.keys().collect();
+            blocks.sort();
+            let mut output = String::new();
+            for block in blocks {
+                output.push_str(&format!("{}:\n", block));
+                lines[block].iter().for_each(|line| output.push_str(&format!("{}\n", line)));
+                output.push('\n');
+            }
+            output
+        }
+        OutputFormat::Json => format!("{}\n", report(function_name, &zones).as_json()),
+        OutputFormat::Dot => dot::function_to_dot(&program.functions[function_name], &lines(&zones)),
+    }
+}
@@ -0,0 +1,79 @@
+use cs260_proj::builder::{pointer_to, FunctionBuilder, ProgramBuilder};
+use cs260_proj::lir::{RelaOp, Type};
+use cs260_proj::cfg::Cfg;
+use cs260_proj::{analyze_constants, analyze_control, analyze_intervals, analyze_rdef, analyze_sccp, interp, ConstValue, Error, IntervalValue};
+
+#[test]
+fn a_store_through_an_int_pointer_may_write_an_address_taken_int() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let x = main.local("x", Type::Int);
+    let p = main.local("p", pointer_to(Type::Int));
+    main.block("entry").addr_of(&p, &x).copy(&x, 1).store(&p, 5).jump("exit");
+    main.block("exit").ret(Some(&x));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    assert_eq!(interp::run(&program, "main", &[], interp::DEFAULT_FUEL).unwrap().ret, Some(interp::Value::Int(5)));
+    let constants = analyze_constants(&program, "main").unwrap();
+    assert_eq!(constants.blocks["exit"]["x"], ConstValue::Top);
+    let intervals = analyze_intervals(&program, "main").unwrap();
+    assert_eq!(intervals.blocks["exit"]["x"], IntervalValue::Top);
+}
+
+#[test]
+fn a_block_reached_with_an_empty_store_is_still_processed() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let x = main.local("x", Type::Int);
+    main.block("entry").jump("bb1");
+    main.block("bb1").copy(&x, 1).jump("exit");
+    main.block("exit").ret(Some(&x));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let constants = analyze_constants(&program, "main").unwrap();
+    assert_eq!(constants.blocks["exit"]["x"], ConstValue::IntConst(1));
+    let intervals = analyze_intervals(&program, "main").unwrap();
+    assert_eq!(intervals.blocks["exit"]["x"], IntervalValue::IntConst(1));
+}
+
+#[test]
+fn a_comparison_of_pointers_may_go_either_way() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let [x, y, c] = ["x", "y", "c"].map(|name| main.local(name, Type::Int));
+    let [p, q] = ["p", "q"].map(|name| main.local(name, pointer_to(Type::Int)));
+    main.block("entry").addr_of(&p, &x).addr_of(&q, &y).cmp(&c, RelaOp::Eq, &p, &q).branch(&c, "same", "other");
+    main.block("same").ret_const(1);
+    main.block("other").ret_const(0);
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let constants = analyze_constants(&program, "main").unwrap();
+    assert_eq!(constants.blocks.keys().collect::<Vec<_>>(), ["entry", "other", "same"]);
+    assert_eq!(constants.blocks["entry"]["c"], ConstValue::Top);
+    let intervals = analyze_intervals(&program, "main").unwrap();
+    assert_eq!(intervals.blocks.keys().collect::<Vec<_>>(), ["entry", "other", "same"]);
+    assert_eq!(intervals.blocks["entry"]["c"], IntervalValue::Top);
+}
+
+#[test]
+fn a_function_without_an_entry_block_is_an_error() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    main.block("entry").ret_const(0);
+    program.add(main);
+    let mut program = program.finish().unwrap();
+    let main = program.functions.get_mut("main").unwrap();
+    let start = main.body.remove("entry").unwrap();
+    main.body.insert("start".to_string(), start);
+
+    assert!(Cfg::new(&program.functions["main"]).rpo.is_empty());
+    let error = Error::NoEntryBlock("main".to_string());
+    assert_eq!(cs260_proj::function(&program, "main").err(), Some(error.clone()));
+    assert_eq!(analyze_constants(&program, "main").err(), Some(error.clone()));
+    assert_eq!(analyze_sccp(&program, "main").err(), Some(error.clone()));
+    assert_eq!(analyze_control(&program, "main").err(), Some(error));
+    assert_eq!(analyze_rdef(&program, "other").err(), Some(Error::UnknownFunction("other".to_string())));
+}
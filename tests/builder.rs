@@ -0,0 +1,38 @@
+use cs260_proj::builder::{pointer_to, FunctionBuilder, ProgramBuilder};
+use cs260_proj::lir::{ArithOp, RelaOp, Type};
+use cs260_proj::{analyze_constants, ConstValue};
+
+#[test]
+fn constants_through_a_join() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let x = main.local("x", Type::Int);
+    let c = main.local("c", Type::Int);
+    main.block("entry").cmp(&c, RelaOp::Less, &p, 0).branch(&c, "bb1", "bb2");
+    main.block("bb1").copy(&x, 3).jump("bb3");
+    main.block("bb2").arith(&x, ArithOp::Add, 1, 2).jump("bb3");
+    main.block("bb3").ret(Some(&x));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let results = analyze_constants(&program, "main").unwrap();
+    assert_eq!(results.blocks["bb3"]["x"], ConstValue::IntConst(3));
+    assert_eq!(results.blocks["bb3"]["c"], ConstValue::Top);
+}
+
+#[test]
+fn finish_rejects_ill_typed_programs() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", None);
+    let q = main.local("q", pointer_to(Type::Int));
+    main.block("entry").arith(&q, ArithOp::Add, 1, 2).jump("missing");
+    program.add(main);
+
+    let errors = program.finish().unwrap_err();
+    let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+    assert_eq!(messages, vec![
+        "main.entry.0: lhs of $arith should have type int but has type &int",
+        "main.entry.1: jump to unknown block missing",
+    ]);
+}
@@ -0,0 +1,47 @@
+use cs260_proj::builder::{pointer_to, FunctionBuilder, ProgramBuilder};
+use cs260_proj::congruence::{gep_indices, render};
+use cs260_proj::lir::{ArithOp, RelaOp, Type};
+use cs260_proj::report::OutputFormat;
+
+#[test]
+fn strided_index_stays_aligned_through_the_loop() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let n = main.param("n", Type::Int);
+    let [i, c] = ["i", "c"].map(|name| main.local(name, Type::Int));
+    let [a, e] = ["a", "e"].map(|name| main.local(name, pointer_to(Type::Int)));
+    main.block("entry").alloc(&a, &n).copy(&i, 0).jump("header");
+    main.block("header").cmp(&c, RelaOp::Less, &i, &n).branch(&c, "body", "exit");
+    main.block("body").gep(&e, &a, &i).store(&e, &i).arith(&i, ArithOp::Add, &i, 4).jump("header");
+    main.block("exit").ret(Some(&i));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    // i + 4 may wrap around, which leaves the interval but not the congruence
+    let index = gep_indices(&program, "main")[&("body".to_string(), 0)];
+    assert!(index.congruence.aligned(2));
+    assert!(!index.congruence.aligned(3));
+    assert_eq!((index.lo, index.hi), (i32::MIN, 2147483644));
+
+    assert_eq!(render(&program, "main", OutputFormat::Text), "\
+body:
+c -> ([0, 1], Top)
+i -> ([-2147483648, 2147483644], 0 mod 4)
+n -> (Top, Top)
+
+entry:
+i -> 0
+n -> (Top, Top)
+
+exit:
+c -> ([0, 1], Top)
+i -> ([-2147483648, 2147483644], 0 mod 4)
+n -> (Top, Top)
+
+header:
+c -> ([0, 1], Top)
+i -> ([-2147483648, 2147483644], 0 mod 4)
+n -> (Top, Top)
+
+");
+}
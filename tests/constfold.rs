@@ -0,0 +1,70 @@
+use cs260_proj::builder::{FunctionBuilder, ProgramBuilder};
+use cs260_proj::constfold::{fold, FoldStats};
+use cs260_proj::interp;
+use cs260_proj::lir::{ArithOp, Program, RelaOp, Type};
+
+#[test]
+fn folds_arithmetic_and_branches() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let x = main.local("x", Type::Int);
+    let y = main.local("y", Type::Int);
+    let z = main.local("z", Type::Int);
+    let c = main.local("c", Type::Int);
+    main.block("entry")
+        .copy(&x, 3)
+        .arith(&y, ArithOp::Multiply, &x, 4)
+        .cmp(&c, RelaOp::Less, &y, 10)
+        .branch(&c, "bb1", "bb2");
+    main.block("bb1").ret(Some(&x));
+    main.block("bb2").arith(&z, ArithOp::Add, &y, &p).ret(Some(&z));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let (folded, stats) = fold(&program);
+    assert_eq!(stats, FoldStats { operands: 2, folded: 2, branches: 1 });
+    assert_eq!(folded.functions["main"].to_string(), "\
+fn main(p:int) -> int {
+let c:int, x:int, y:int, z:int
+bb1:
+  $ret x
+bb2:
+  z = $arith add 12 p
+  $ret z
+entry:
+  x = $copy 3
+  y = $copy 12
+  c = $copy 0
+  $jump bb2
+}
+
+");
+    let reparsed = Program::parse_json(&folded.as_json());
+    assert_eq!(reparsed.to_string(), folded.to_string());
+    for arg in [-5, 0, 7] {
+        let before = interp::run(&program, "main", &[arg], 1000).unwrap();
+        let after = interp::run(&reparsed, "main", &[arg], 1000).unwrap();
+        assert_eq!(before.ret, after.ret);
+    }
+}
+
+#[test]
+fn an_unset_local_folds_as_zero() {
+    // x is only written on one path, and read as the zero every local starts with on the other
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let x = main.local("x", Type::Int);
+    main.block("entry").branch(&p, "bb1", "bb2");
+    main.block("bb1").copy(&x, 5).jump("bb3");
+    main.block("bb2").jump("bb3");
+    main.block("bb3").ret(Some(&x));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let (folded, stats) = fold(&program);
+    assert_eq!(stats, FoldStats::default());
+    let inputs = [vec![0], vec![1]];
+    assert_eq!(interp::compare(&program, &folded, "main", &inputs, interp::DEFAULT_FUEL), Ok(()));
+}
@@ -0,0 +1,62 @@
+use cs260_proj::builder::{pointer_to, FunctionBuilder, ProgramBuilder};
+use cs260_proj::copyprop::{propagate, Scope};
+use cs260_proj::cse;
+use cs260_proj::interp;
+use cs260_proj::lir::{ArithOp, Program, Type};
+
+fn program() -> Program {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let x = main.local("x", Type::Int);
+    let y = main.local("y", Type::Int);
+    let a = main.local("a", Type::Int);
+    let b = main.local("b", Type::Int);
+    let q = main.local("q", pointer_to(Type::Int));
+    main.block("entry")
+        .copy(&x, &p)
+        .copy(&y, &x)
+        .arith(&a, ArithOp::Add, &y, 1)
+        .jump("bb1");
+    main.block("bb1")
+        .arith(&b, ArithOp::Add, &y, 1)
+        .addr_of(&q, &p)
+        .store(&q, 5)
+        .arith(&x, ArithOp::Add, &p, 1)
+        .arith(&x, ArithOp::Multiply, &x, &b)
+        .ret(Some(&x));
+    program.add(main);
+    program.finish().unwrap()
+}
+
+#[test]
+fn copies_and_expressions_are_reused_until_written() {
+    let program = program();
+    let (propagated, replaced) = propagate(&program, Scope::Global);
+    let (eliminated, eliminations) = cse::eliminate(&propagated);
+    assert_eq!((replaced, eliminations), (3, 1));
+    // the store through q may write p, so p + 1 is computed again afterwards
+    assert_eq!(eliminated.functions["main"].to_string(), "\
+fn main(p:int) -> int {
+let a:int, b:int, q:&int, x:int, y:int
+bb1:
+  b = $copy a
+  q = $addrof p
+  $store q 5
+  x = $arith add p 1
+  x = $arith mul x b
+  $ret x
+entry:
+  x = $copy p
+  y = $copy p
+  a = $arith add p 1
+  $jump bb1
+}
+
+");
+    let inputs: Vec<Vec<i32>> = (-2..3).map(|p| vec![p]).collect();
+    assert_eq!(interp::compare(&program, &eliminated, "main", &inputs, 1000), Ok(()));
+
+    let (local, _) = propagate(&program, Scope::Local);
+    assert!(local.functions["main"].to_string().contains("b = $arith add y 1"));
+}
@@ -0,0 +1,63 @@
+use cs260_proj::builder::{pointer_to, FunctionBuilder, ProgramBuilder};
+use cs260_proj::dce::{eliminate, DceReport};
+use cs260_proj::interp::{self, DEFAULT_FUEL};
+use cs260_proj::lir::{ArithOp, Type};
+
+#[test]
+fn removes_dead_code_and_merges_jumps() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let x = main.local("x", Type::Int);
+    let y = main.local("y", Type::Int);
+    let z = main.local("z", Type::Int);
+    main.local("w", Type::Int);
+    main.block("entry").copy(&x, 1).copy(&x, 2).arith(&y, ArithOp::Add, &p, 1).jump("bb1");
+    main.block("bb1").arith(&z, ArithOp::Add, &x, &p).branch(&z, "bb2", "bb3");
+    main.block("bb2").jump("bb4");
+    main.block("bb3").ret(Some(&p));
+    main.block("bb4").ret(Some(&z));
+    main.block("bb5").ret(Some(&x));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let (reduced, report) = eliminate(&program);
+    assert_eq!(report, DceReport {
+        unreachable_blocks: vec!["main.bb5".to_string()],
+        dead_instructions: vec!["main.entry: x = $copy 1".to_string(), "main.entry: y = $arith add p 1".to_string()],
+        unused_locals: vec!["main.y".to_string(), "main.w".to_string()],
+        merged_blocks: vec!["main.bb1".to_string(), "main.bb2".to_string()],
+    });
+    assert_eq!(reduced.functions["main"].to_string(), "\
+fn main(p:int) -> int {
+let x:int, z:int
+bb3:
+  $ret p
+bb4:
+  $ret z
+entry:
+  x = $copy 2
+  z = $arith add x p
+  $branch z bb4 bb3
+}
+
+");
+}
+
+#[test]
+fn keeps_dead_loads_that_may_fault() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let n = main.param("n", Type::Int);
+    let a = main.local("a", pointer_to(Type::Int));
+    let e = main.local("e", pointer_to(Type::Int));
+    let q = main.local("q", pointer_to(Type::Int));
+    let x = main.local("x", Type::Int);
+    main.block("entry").alloc(&a, 1).gep(&e, &a, &n).load(&x, &e).load(&x, &q).ret_const(0);
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let (reduced, report) = eliminate(&program);
+    assert_eq!(report, DceReport::default());
+    assert_eq!(interp::compare(&program, &reduced, "main", &[vec![0], vec![1]], DEFAULT_FUEL), Ok(()));
+}
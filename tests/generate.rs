@@ -0,0 +1,45 @@
+use cs260_proj::fuzz::{self, FuzzConfig};
+use cs260_proj::generate::{generate, GenConfig};
+use cs260_proj::lir::Type;
+use cs260_proj::reaching::{Effects, Memory};
+use cs260_proj::{interp, validate};
+
+#[test]
+fn generated_programs_are_valid_and_terminate() {
+    let config = GenConfig::default();
+    for seed in 0..50 {
+        let program = generate(seed, &config);
+        assert_eq!(validate::validate(&program), Ok(()), "seed {}", seed);
+        assert_eq!(program.to_string(), generate(seed, &config).to_string(), "seed {} is not deterministic", seed);
+        let main = &program.functions["main"];
+        let args = vec![3; main.params.len()];
+        assert!(interp::run(&program, "main", &args, interp::DEFAULT_FUEL).is_ok(), "seed {}", seed);
+    }
+}
+
+#[test]
+fn analyses_are_sound_and_terminate_on_generated_programs() {
+    let report = fuzz::fuzz(&FuzzConfig { count: 30, ..Default::default() });
+    let findings: Vec<String> = report.findings.iter().map(|f| f.to_string()).collect();
+    assert_eq!(findings, Vec::<String>::new());
+    assert_eq!(report.programs, 30);
+}
+
+#[test]
+fn generated_entry_blocks_define_every_int_local() {
+    let config = GenConfig::default();
+    for seed in 0..20 {
+        let program = generate(seed, &config);
+        for function in program.functions.values() {
+            let memory = Memory::new(&program, function);
+            let defined: Vec<String> = function.body["entry"].insts.iter()
+                .flat_map(|inst| Effects::of_inst(inst, &memory).strong_defs)
+                .collect();
+            let undefined: Vec<&str> = function.locals.iter()
+                .filter(|v| v.typ == Type::Int && !defined.contains(&v.name))
+                .map(|v| v.name.as_str())
+                .collect();
+            assert_eq!(undefined, Vec::<&str>::new(), "seed {} function {}", seed, function.id);
+        }
+    }
+}
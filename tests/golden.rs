@@ -0,0 +1,11 @@
+use std::path::Path;
+use cs260_proj::golden;
+
+// bless changed expectations with `cargo run -- --update`
+#[test]
+fn golden_outputs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let (passed, failed) = golden::check_dir(dir, false).unwrap();
+    assert!(passed > 0, "no golden tests found");
+    assert!(failed.is_empty(), "golden tests failed: {}", failed.join(", "));
+}
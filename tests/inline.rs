@@ -0,0 +1,54 @@
+use cs260_proj::builder::{FunctionBuilder, ProgramBuilder};
+use cs260_proj::inline::inline;
+use cs260_proj::interp;
+use cs260_proj::lir::{ArithOp, RelaOp, Type};
+
+#[test]
+fn inlines_small_non_recursive_callees() {
+    let mut program = ProgramBuilder::new();
+    let mut sq = FunctionBuilder::new("sq", Some(Type::Int));
+    let a = sq.param("a", Type::Int);
+    let r = sq.local("r", Type::Int);
+    sq.block("entry").arith(&r, ArithOp::Multiply, &a, &a).ret(Some(&r));
+    program.add(sq);
+
+    let mut fact = FunctionBuilder::new("fact", Some(Type::Int));
+    let n = fact.param("n", Type::Int);
+    let [c, m, r] = ["c", "m", "r"].map(|name| fact.local(name, Type::Int));
+    fact.block("entry").cmp(&c, RelaOp::LessEq, &n, 1).branch(&c, "base", "rec");
+    fact.block("base").ret_const(1);
+    fact.block("rec").arith(&m, ArithOp::Subtract, &n, 1).call(Some(&r), "fact", vec![(&m).into()], "done");
+    fact.block("done").arith(&r, ArithOp::Multiply, &n, &r).ret(Some(&r));
+    program.add(fact);
+
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let x = main.param("x", Type::Int);
+    let [y, z] = ["y", "z"].map(|name| main.local(name, Type::Int));
+    main.block("entry").call(Some(&y), "sq", vec![(&x).into()], "bb1");
+    main.block("bb1").call(Some(&z), "fact", vec![(&y).into()], "bb2");
+    main.block("bb2").ret(Some(&z));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let (inlined, calls) = inline(&program);
+    assert_eq!(calls, 1);
+    assert_eq!(inlined.functions["main"].to_string(), "\
+fn main(x:int) -> int {
+let sq_1_a:int, sq_1_r:int, y:int, z:int
+bb1:
+  z = $call_dir fact(y) then bb2
+bb2:
+  $ret z
+entry:
+  sq_1_a = $copy x
+  $jump sq_1_entry
+sq_1_entry:
+  sq_1_r = $arith mul sq_1_a sq_1_a
+  y = $copy sq_1_r
+  $jump bb1
+}
+
+");
+    assert_eq!(inlined.functions["fact"].to_string(), program.functions["fact"].to_string());
+    interp::compare(&program, &inlined, "main", &[vec![2], vec![3]], interp::DEFAULT_FUEL).unwrap();
+}
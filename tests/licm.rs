@@ -0,0 +1,55 @@
+use cs260_proj::builder::{pointer_to, FunctionBuilder, ProgramBuilder};
+use cs260_proj::licm::hoist;
+use cs260_proj::lir::{ArithOp, Program, RelaOp, Type};
+
+fn counting_loop(store: bool) -> Program {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let q = main.param("q", pointer_to(Type::Int));
+    let [i, s, v, c, t, u] = ["i", "s", "v", "c", "t", "u"].map(|name| main.local(name, Type::Int));
+    main.block("entry").copy(&i, 0).copy(&s, 0).jump("header");
+    main.block("header").load(&v, &q).cmp(&c, RelaOp::Less, &i, &v).branch(&c, "body", "exit");
+    let body = main.block("body").arith(&t, ArithOp::Multiply, &p, 2).arith(&u, ArithOp::Add, &t, 1)
+        .arith(&s, ArithOp::Add, &s, &u).arith(&i, ArithOp::Add, &i, 1);
+    if store { body.store(&q, &s).jump("header") } else { body.jump("header") }
+    main.block("exit").ret(Some(&s));
+    program.add(main);
+    program.finish().unwrap()
+}
+
+#[test]
+fn hoists_invariant_computations_into_a_preheader() {
+    let (hoisted, moved) = hoist(&counting_loop(false));
+    assert_eq!(moved, 3);
+    assert_eq!(hoisted.functions["main"].to_string(), "\
+fn main(p:int, q:&int) -> int {
+let c:int, i:int, s:int, t:int, u:int, v:int
+body:
+  s = $arith add s u
+  i = $arith add i 1
+  $jump header
+entry:
+  i = $copy 0
+  s = $copy 0
+  $jump header_preheader
+exit:
+  $ret s
+header:
+  c = $cmp lt i v
+  $branch c body exit
+header_preheader:
+  v = $load q
+  t = $arith mul p 2
+  u = $arith add t 1
+  $jump header
+}
+
+");
+
+    // the store in the loop may write what q points to
+    let (hoisted, moved) = hoist(&counting_loop(true));
+    assert_eq!(moved, 2);
+    let header = &hoisted.functions["main"].body["header"];
+    assert_eq!(header.insts[0].to_string(), "v = $load q");
+}
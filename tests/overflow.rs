@@ -0,0 +1,72 @@
+use cs260_proj::builder::{FunctionBuilder, ProgramBuilder};
+use cs260_proj::lir::{ArithOp, Program, RelaOp, Type};
+use cs260_proj::overflow::overflow_warnings;
+use cs260_proj::report::OutputFormat;
+use cs260_proj::{constants, interp, intervals, sccp};
+
+fn wrapping() -> Program {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let n = main.param("n", Type::Int);
+    let [a, b, d, i, c] = ["a", "b", "d", "i", "c"].map(|name| main.local(name, Type::Int));
+    main.block("entry").copy(&a, i32::MAX).arith(&b, ArithOp::Add, &a, 1)
+        .arith(&d, ArithOp::Divide, i32::MIN, -1).copy(&i, 0).jump("header");
+    main.block("header").cmp(&c, RelaOp::Less, &i, &n).branch(&c, "body", "exit");
+    main.block("body").arith(&i, ArithOp::Add, &i, 1).jump("header");
+    main.block("exit").arith(&i, ArithOp::Multiply, &i, 2).ret(Some(&b));
+    program.add(main);
+    program.finish().unwrap()
+}
+
+#[test]
+fn constants_wrap_like_the_interpreter() {
+    let program = wrapping();
+    for render in [constants::render, intervals::render, sccp::render] {
+        let output = render(&program, "main", OutputFormat::Text);
+        assert!(output.contains("b -> -2147483648\n"), "{}", output);
+        assert!(output.contains("d -> -2147483648\n"), "{}", output);
+    }
+}
+
+#[test]
+fn warns_where_arithmetic_may_wrap_around() {
+    // the increment is guarded by i < n, but the doubling after the loop is not
+    let warnings: Vec<String> = overflow_warnings(&wrapping(), "main").iter().map(|w| w.to_string()).collect();
+    assert_eq!(warnings, [
+        "warning: main.entry.1: b = $arith add a 1 overflows",
+        "warning: main.entry.2: d = $arith div -2147483648 -1 overflows",
+        "warning: main.exit.0: i = $arith mul i 2 may overflow",
+    ]);
+}
+
+#[test]
+fn an_input_may_overflow_against_a_constant() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let q = main.param("q", Type::Int);
+    let [a, b, c, d, e, f, g] = ["a", "b", "c", "d", "e", "f", "g"].map(|name| main.local(name, Type::Int));
+    main.block("entry")
+        .arith(&a, ArithOp::Multiply, &p, 65536)
+        .arith(&b, ArithOp::Add, &p, 1)
+        .arith(&c, ArithOp::Divide, &p, -1)
+        .arith(&d, ArithOp::Add, &p, 0)
+        .arith(&e, ArithOp::Subtract, -1, &p)
+        .arith(&f, ArithOp::Divide, &p, 2)
+        .arith(&g, ArithOp::Add, &p, &q)
+        .ret(Some(&a));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    // p = 2147483647 makes p * 65536 wrap around to -65536
+    let ret = interp::run(&program, "main", &[i32::MAX, 0], interp::DEFAULT_FUEL).unwrap().ret;
+    assert_eq!(ret, Some(interp::Value::Int(-65536)));
+    // adding 0, -1 - p and halving stay in range, but the sum of two inputs may not
+    let warnings: Vec<String> = overflow_warnings(&program, "main").iter().map(|w| w.to_string()).collect();
+    assert_eq!(warnings, [
+        "warning: main.entry.0: a = $arith mul p 65536 may overflow",
+        "warning: main.entry.1: b = $arith add p 1 may overflow",
+        "warning: main.entry.2: c = $arith div p -1 may overflow",
+        "warning: main.entry.6: g = $arith add p q may overflow",
+    ]);
+}
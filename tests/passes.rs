@@ -0,0 +1,42 @@
+use cs260_proj::builder::{FunctionBuilder, ProgramBuilder};
+use cs260_proj::lir::{ArithOp, Type};
+use cs260_proj::passes::{parse_pipeline, PassManager};
+
+#[test]
+fn pipeline_runs_to_a_fixpoint() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let x = main.local("x", Type::Int);
+    let y = main.local("y", Type::Int);
+    let z = main.local("z", Type::Int);
+    let c = main.local("c", Type::Int);
+    main.block("entry").copy(&x, 1).arith(&y, ArithOp::Add, &x, 2).arith(&c, ArithOp::Subtract, &y, 3).branch(&c, "bb1", "bb2");
+    main.block("bb1").copy(&z, &p).jump("bb3");
+    main.block("bb2").arith(&z, ArithOp::Add, &p, &y).jump("bb3");
+    main.block("bb3").ret(Some(&z));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let mut manager = PassManager::new(parse_pipeline("constfold, dce,copyprop").unwrap());
+    manager.fixpoint = true;
+    let (optimized, report) = manager.run(&program).unwrap();
+    assert_eq!(optimized.functions["main"].to_string(), "\
+fn main(p:int) -> int {
+let z:int
+entry:
+  z = $arith add p 3
+  $ret z
+}
+
+");
+    assert!(report.converged);
+    assert_eq!(report.rounds, 2);
+    assert_eq!(report.runs.len(), 6);
+    assert!(report.runs[3..].iter().all(|run| run.changes == 0));
+    assert_eq!(report.runs.first().unwrap().before.instr_num, 5);
+    assert_eq!(report.runs.last().unwrap().after.instr_num, 1);
+
+    assert!(parse_pipeline("constfold,licm2").is_err());
+    assert!(parse_pipeline("dce,,cse").is_err());
+}
@@ -0,0 +1,41 @@
+use cs260_proj::dependence::{Pdg, PDG_ENTRY};
+use cs260_proj::lir::Program;
+use serde_json::Value;
+
+// main(p) {
+// entry: c = p < 0; branch c then join
+// then: x = 1; jump join
+// join: ret x }
+fn program() -> Program {
+    Program::parse_json(include_str!("programs/pdg.lir.json"))
+}
+
+// (from, to, kind, label) of every edge in the json output
+fn edges(json: &Value) -> Vec<(String, String, String, String)> {
+    json["edges"].as_array().unwrap().iter().map(|edge| {
+        let field = |name: &str| edge[name].as_str().unwrap().to_string();
+        (field("from"), field("to"), field("kind"), field("label"))
+    }).collect()
+}
+
+#[test]
+fn json_lists_the_data_and_control_edges_of_a_branch() {
+    let json: Value = serde_json::from_str(&Pdg::new(&program(), "main").as_json()).unwrap();
+    assert_eq!(json["function"], "main");
+
+    let nodes: Vec<&str> = json["nodes"].as_array().unwrap().iter().map(|n| n["id"].as_str().unwrap()).collect();
+    assert_eq!(nodes, vec![PDG_ENTRY, "entry.0", "entry.1", "join.0", "then.0", "then.1"]);
+
+    let edge = |from: &str, to: &str, kind: &str, label: &str| {
+        (from.to_string(), to.to_string(), kind.to_string(), label.to_string())
+    };
+    assert_eq!(edges(&json), vec![
+        edge("ENTRY", "entry.0", "Control", ""),
+        edge("ENTRY", "entry.1", "Control", ""),
+        edge("ENTRY", "join.0", "Control", ""),
+        edge("entry.0", "entry.1", "Data", "c"),
+        edge("entry.1", "then.0", "Control", "true"),
+        edge("entry.1", "then.1", "Control", "true"),
+        edge("then.0", "join.0", "Data", "x"),
+    ]);
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeSet;
+use cs260_proj::defuse::DefUse;
+use cs260_proj::lir::Program;
+use cs260_proj::reaching::{reaching_defs, ProgramPoint};
+
+// main(p) {
+// entry: x = 1; x = 2; q = &y; y = 3; *q = x; c = p < 0; branch c bb1 bb2
+// bb1: x = x + 1; jump bb3
+// bb2: jump bb3
+// bb3: ret x }
+fn program() -> Program {
+    Program::parse_json(include_str!("programs/reaching.lir.json"))
+}
+
+fn point(block: &str, index: usize) -> ProgramPoint {
+    ProgramPoint::new("main", block, index)
+}
+
+fn points(points: &[(&str, usize)]) -> BTreeSet<ProgramPoint> {
+    points.iter().map(|(block, index)| point(block, *index)).collect()
+}
+
+#[test]
+fn a_strong_definition_kills_the_previous_one() {
+    let (stores, _) = reaching_defs(&program(), "main");
+    assert_eq!(stores[&point("entry", 1)]["x"], points(&[("entry", 0)]));
+    assert_eq!(stores[&point("entry", 2)]["x"], points(&[("entry", 1)]));
+    assert_eq!(stores[&point("bb3", 0)]["x"], points(&[("entry", 1), ("bb1", 0)]));
+}
+
+#[test]
+fn a_store_through_an_int_pointer_keeps_earlier_definitions_alive() {
+    let (stores, _) = reaching_defs(&program(), "main");
+    assert_eq!(stores[&point("entry", 5)]["y"], points(&[("entry", 3), ("entry", 4)]));
+    // the store is not a definition of the pointer itself
+    assert_eq!(stores[&point("entry", 5)]["q"], points(&[("entry", 2)]));
+}
+
+#[test]
+fn chains_link_terminal_operands_to_their_definitions() {
+    let chains = DefUse::new(&program(), "main");
+    let reaching: Vec<&ProgramPoint> = chains.reaching(&point("entry", 6), "c").collect();
+    assert_eq!(reaching, vec![&point("entry", 5)]);
+    let reaching: Vec<&ProgramPoint> = chains.reaching(&point("bb3", 0), "x").collect();
+    assert_eq!(reaching, vec![&point("bb1", 0), &point("entry", 1)]);
+    let uses: Vec<&ProgramPoint> = chains.uses_of(&point("entry", 5), "c").collect();
+    assert_eq!(uses, vec![&point("entry", 6)]);
+}
+
+#[test]
+fn dead_definitions_and_params_have_no_chains() {
+    let chains = DefUse::new(&program(), "main");
+    // x = 1 is overwritten before any read, but still listed as a definition point
+    assert_eq!(chains.uses_of(&point("entry", 0), "x").count(), 0);
+    assert!(chains.def_points().any(|def| def == &point("entry", 0)));
+    // p is a param, so the comparison reads nothing with a defining point
+    assert_eq!(chains.defs_at(&point("entry", 5)).count(), 0);
+    assert!(!chains.use_points().any(|use_point| use_point == &point("entry", 5)));
+}
+
+#[test]
+fn iteration_helpers_cover_every_variable() {
+    let chains = DefUse::new(&program(), "main");
+    let uses: Vec<&ProgramPoint> = chains.all_uses(&point("entry", 1)).into_iter().collect();
+    assert_eq!(uses, vec![&point("bb1", 0), &point("bb3", 0), &point("entry", 4)]);
+    let defs: Vec<&ProgramPoint> = chains.all_reaching(&point("entry", 4)).into_iter().collect();
+    assert_eq!(defs, vec![&point("entry", 1), &point("entry", 2)]);
+    let vars: Vec<&String> = chains.uses_at(&point("entry", 2)).map(|(var, _)| var).collect();
+    assert_eq!(vars, vec!["q"]);
+    // nothing reads y after the store, but the store still counts as a definition
+    assert_eq!(chains.uses_at(&point("entry", 4)).count(), 0);
+    assert!(chains.def_points().any(|def| def == &point("entry", 4)));
+    assert_eq!(chains.block_entry("bb3").unwrap()["x"], points(&[("entry", 1), ("bb1", 0)]));
+    assert!(chains.block_entry("missing").is_none());
+}
+
+#[test]
+fn program_points_print_and_parse_as_block_dot_index() {
+    assert_eq!(point("bb1", 0).to_string(), "bb1.0");
+    assert_eq!(ProgramPoint::parse("main", "bb1.0"), Some(point("bb1", 0)));
+    assert_eq!(ProgramPoint::parse("main", "bb1"), None);
+}
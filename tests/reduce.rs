@@ -0,0 +1,50 @@
+use cs260_proj::builder::{FunctionBuilder, ProgramBuilder};
+use cs260_proj::interp::{self, Value};
+use cs260_proj::lir::{ArithOp, Type};
+use cs260_proj::reduce::reduce;
+
+#[test]
+fn reduces_to_what_the_predicate_needs() {
+    let mut program = ProgramBuilder::new();
+    program.global("g", Type::Int);
+    program.external("output", None, vec![Type::Int]);
+    let mut helper = FunctionBuilder::new("helper", None);
+    helper.block("entry").ret(None);
+    program.add(helper);
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let x = main.local("x", Type::Int);
+    let y = main.local("y", Type::Int);
+    let z = main.local("z", Type::Int);
+    let w = main.local("w", Type::Int);
+    main.block("entry")
+        .copy(&x, 3)
+        .copy(&w, 1)
+        .copy(&y, 4)
+        .call_ext(None, "output", vec![(&w).into()])
+        .arith(&z, ArithOp::Add, &x, &y)
+        .branch(&w, "bb1", "bb2");
+    main.block("bb1").ret(Some(&z));
+    main.block("bb2").call(None, "helper", vec![], "bb3");
+    main.block("bb3").ret(Some(&w));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let returns_seven = |p: &cs260_proj::lir::Program| {
+        matches!(interp::run(p, "main", &[], 1000), Ok(outcome) if outcome.ret == Some(Value::Int(7)))
+    };
+    let reduction = reduce(&program, returns_seven);
+    assert_eq!(reduction.program.to_string(), "\
+fn main() -> int {
+let x:int, y:int, z:int
+bb1:
+  $ret z
+entry:
+  x = $copy 3
+  y = $copy 4
+  z = $arith add x y
+  $jump bb1
+}
+
+");
+    assert!(reduction.accepted <= reduction.attempts);
+}
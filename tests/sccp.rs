@@ -0,0 +1,70 @@
+use cs260_proj::builder::{FunctionBuilder, ProgramBuilder};
+use cs260_proj::lir::{Program, RelaOp, Type};
+use cs260_proj::{analyze_constants, analyze_sccp, ConstValue};
+
+// main(p) { entry: r = x; branch p body <other> } where body sets x to 5 and jumps back to entry
+fn entry_loop(other: Option<i32>) -> Program {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let r = main.local("r", Type::Int);
+    let x = main.local("x", Type::Int);
+    match other {
+        Some(value) => {
+            main.block("entry").copy(&r, &x).branch(&p, "body", "b2");
+            main.block("b2").copy(&x, value).branch(&p, "entry", "done");
+        }
+        None => main.block("entry").copy(&r, &x).branch(&p, "body", "done"),
+    }
+    main.block("body").copy(&x, 5).jump("entry");
+    main.block("done").ret(Some(&r));
+    program.add(main);
+    program.finish().unwrap()
+}
+
+#[test]
+fn values_flow_around_a_back_edge_into_entry() {
+    let program = entry_loop(None);
+    let sccp = analyze_sccp(&program, "main").unwrap();
+    assert_eq!(sccp.blocks["entry"]["x"], ConstValue::IntConst(5));
+    assert_eq!(sccp.blocks["done"]["r"], ConstValue::IntConst(5));
+    assert_eq!(sccp.blocks, analyze_constants(&program, "main").unwrap().blocks);
+}
+
+#[test]
+fn params_keep_their_value_when_entry_has_several_predecessors() {
+    let program = entry_loop(Some(7));
+    let sccp = analyze_sccp(&program, "main").unwrap();
+    let blocks: Vec<&String> = sccp.blocks.keys().collect();
+    assert_eq!(blocks, vec!["b2", "body", "done", "entry"]);
+    assert_eq!(sccp.blocks["entry"]["p"], ConstValue::Top);
+    assert_eq!(sccp.blocks["entry"]["x"], ConstValue::Top);
+    assert_eq!(sccp.blocks["done"]["x"], ConstValue::IntConst(7));
+    assert_eq!(sccp.blocks, analyze_constants(&program, "main").unwrap().blocks);
+}
+
+// x stays 1 around the loop only because the branch that would change it is never taken
+#[test]
+fn a_constant_branch_leaves_the_other_side_dead() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let p = main.param("p", Type::Int);
+    let x = main.local("x", Type::Int);
+    let c = main.local("c", Type::Int);
+    main.block("entry").copy(&x, 1).jump("head");
+    main.block("head").cmp(&c, RelaOp::Eq, &x, 1).branch(&c, "same", "diff");
+    main.block("same").jump("next");
+    main.block("diff").copy(&x, 2).jump("next");
+    main.block("next").branch(&p, "head", "done");
+    main.block("done").ret(Some(&x));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    let sccp = analyze_sccp(&program, "main").unwrap();
+    let blocks: Vec<&String> = sccp.blocks.keys().collect();
+    assert_eq!(blocks, vec!["done", "entry", "head", "next", "same"]);
+    assert_eq!(sccp.blocks["head"]["c"], ConstValue::IntConst(1));
+    assert_eq!(sccp.blocks["next"]["x"], ConstValue::IntConst(1));
+    assert_eq!(sccp.blocks["done"]["x"], ConstValue::IntConst(1));
+    assert_eq!(sccp.blocks["done"]["p"], ConstValue::Top);
+}
@@ -0,0 +1,54 @@
+use cs260_proj::builder::{FunctionBuilder, ProgramBuilder};
+use cs260_proj::lir::{ArithOp, RelaOp, Type};
+use cs260_proj::report::OutputFormat;
+use cs260_proj::signparity::{render, render_reduced};
+
+#[test]
+fn reduced_product_prunes_what_neither_side_can() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let y = main.param("y", Type::Int);
+    let [z, o, c] = ["z", "o", "c"].map(|name| main.local(name, Type::Int));
+    main.block("entry").arith(&z, ArithOp::Multiply, &y, 2).arith(&o, ArithOp::Add, &z, 1)
+        .cmp(&c, RelaOp::Eq, &o, 0).branch(&c, "zero", "done");
+    main.block("zero").ret_const(0);
+    main.block("done").ret(Some(&o));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    assert_eq!(render(&program, "main", OutputFormat::Text), "\
+done:
+c -> (NonNeg, Even)
+o -> (Top, Odd)
+y -> (Top, Top)
+z -> (Top, Even)
+
+entry:
+c -> (NonNeg, Even)
+o -> (Top, Odd)
+y -> (Top, Top)
+z -> (Top, Even)
+
+zero:
+c -> (NonNeg, Even)
+o -> (Top, Odd)
+y -> (Top, Top)
+z -> (Top, Even)
+
+");
+    // an odd int is not zero, so it is never equal to zero
+    assert_eq!(render_reduced(&program, "main", OutputFormat::Text), "\
+done:
+c -> (Zero, Even)
+o -> (NonZero, Odd)
+y -> (Top, Top)
+z -> (Top, Even)
+
+entry:
+c -> (Zero, Even)
+o -> (NonZero, Odd)
+y -> (Top, Top)
+z -> (Top, Even)
+
+");
+}
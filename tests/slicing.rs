@@ -0,0 +1,61 @@
+use std::collections::BTreeSet;
+use cs260_proj::lir::Program;
+use cs260_proj::reaching::ProgramPoint;
+use cs260_proj::slicing::{Direction, Slicer};
+use serde_json::Value;
+use cs260_proj::{interp, validate};
+
+// main(p) {
+// entry: x = 1; y = 2; z = 7; c = p < 0; branch c then join
+// then: x = x + y; jump join
+// join: r = x * 2; ret r }
+fn program() -> Program {
+    Program::parse_json(include_str!("programs/slicing.lir.json"))
+}
+
+fn points(points: &[(&str, usize)]) -> BTreeSet<ProgramPoint> {
+    points.iter().map(|(block, index)| ProgramPoint::new("main", block, *index)).collect()
+}
+
+#[test]
+fn a_backward_slice_keeps_the_data_definitions_and_the_controlling_branch() {
+    let program = program();
+    let slicer = Slicer::new(&program, "main");
+    let slice = slicer.slice(&ProgramPoint::new("main", "join", 1), "r", Direction::Backward);
+    assert_eq!(slice, points(&[
+        ("entry", 0), ("entry", 1), ("entry", 3), ("entry", 4),
+        ("then", 0),
+        ("join", 0), ("join", 1),
+    ]));
+}
+
+#[test]
+fn a_forward_slice_contains_the_uses() {
+    let program = program();
+    let slicer = Slicer::new(&program, "main");
+    let slice = slicer.slice(&ProgramPoint::new("main", "entry", 1), "y", Direction::Forward);
+    assert_eq!(slice, points(&[("entry", 1), ("then", 0), ("join", 0), ("join", 1)]));
+    // a branch condition reaches every point of the blocks it decides
+    let slice = slicer.slice(&ProgramPoint::new("main", "entry", 3), "c", Direction::Forward);
+    assert_eq!(slice, points(&[("entry", 3), ("entry", 4), ("then", 0), ("then", 1), ("join", 0), ("join", 1)]));
+}
+
+#[test]
+fn a_sliced_program_round_trips_through_json_and_validates() {
+    let program = program();
+    let slicer = Slicer::new(&program, "main");
+    let slice = slicer.slice(&ProgramPoint::new("main", "join", 1), "r", Direction::Backward);
+    let sliced = slicer.sliced_program(&program, &slice);
+    assert_eq!(sliced.functions["main"].body["entry"].insts.len(), 3);
+
+    let parsed = Program::parse_json(&sliced.as_json());
+    let json = |program: &Program| serde_json::from_str::<Value>(&program.as_json()).unwrap();
+    assert_eq!(json(&parsed), json(&sliced));
+    assert_eq!(validate::validate(&parsed), Ok(()));
+    // the slice computes the same result as the whole program
+    for p in [-1, 1] {
+        let whole = interp::run(&program, "main", &[p], interp::DEFAULT_FUEL).unwrap().ret;
+        let part = interp::run(&parsed, "main", &[p], interp::DEFAULT_FUEL).unwrap().ret;
+        assert_eq!(part, whole, "p = {}", p);
+    }
+}
@@ -0,0 +1,49 @@
+use cs260_proj::builder::{pointer_to, FunctionBuilder, ProgramBuilder};
+use cs260_proj::lir::{ArithOp, RelaOp, Type};
+use cs260_proj::report::OutputFormat;
+use cs260_proj::zones::{render, Zones};
+
+#[test]
+fn proves_the_index_of_a_guarded_loop_in_bounds() {
+    let mut program = ProgramBuilder::new();
+    let mut main = FunctionBuilder::new("main", Some(Type::Int));
+    let n = main.param("n", Type::Int);
+    let [i, c] = ["i", "c"].map(|name| main.local(name, Type::Int));
+    let [a, e] = ["a", "e"].map(|name| main.local(name, pointer_to(Type::Int)));
+    main.block("entry").alloc(&a, &n).copy(&i, 0).jump("header");
+    main.block("header").cmp(&c, RelaOp::Less, &i, &n).branch(&c, "body", "exit");
+    main.block("body").gep(&e, &a, &i).store(&e, &i).arith(&i, ArithOp::Add, &i, 1).jump("header");
+    main.block("exit").ret(Some(&i));
+    program.add(main);
+    let program = program.finish().unwrap();
+
+    // at the $gep, 0 <= i < n
+    let zones = Zones::new(&program, "main");
+    let at_gep = zones.before(&program, "main", "body", 0).unwrap();
+    let facts = zones.facts(&at_gep);
+    assert_eq!(facts["i"], "[0, 2147483646]");
+    assert_eq!(facts["i - n"], "<= -1");
+
+    assert_eq!(render(&program, "main", OutputFormat::Text), "\
+body:
+c -> 1
+i -> [1, 2147483647]
+i - n <= 0
+n -> [1, 2147483647]
+
+entry:
+i -> 0
+
+exit:
+c -> 0
+i -> [0, 2147483647]
+i - n <= 2147483648
+n - i <= 0
+
+header:
+c -> [0, 1]
+i -> [0, 2147483647]
+i - n <= 2147483648
+
+");
+}